@@ -1,18 +1,415 @@
-use enigo::*;
+use chrono::{DateTime, Local, NaiveTime, SubsecRound};
+use enigo::{Axis, Coordinate, Direction};
 use serde::{Deserialize, Serialize};
 use serde_json;
 use std::{
+    collections::HashMap,
     fs::File,
     io::{Error, Read, Write},
-    path::Path,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
 };
-use std::{thread, time};
 
+use crate::backend::{InputBackend, InputEvent};
+use crate::clock::Clock;
 use crate::errors::AppError;
+use crate::expr::{Expr, Param, Value};
+use crate::ranged::{Number, Ranged};
+use crate::screen::{Color, NoScreen, Screen};
+use crate::signal::{ControlSignal, Progress, Step};
+use crate::simulation::TimelineEntry;
+
+/// Position of the executor inside one `LoopAction`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LoopFrame {
+    /// Number of the loop as labeled in the editor, the root loop is 1
+    pub loop_index: u32,
+    /// Index of the action that is currently executed
+    pub action: usize,
+    pub iteration: u64,
+}
+
+/// Everything an action needs while it is being executed.
+pub struct ExecutionContext<'a> {
+    pub backend: &'a mut dyn InputBackend,
+    pub clock: &'a dyn Clock,
+    /// Conditions look at this screen
+    pub screen: &'a dyn Screen,
+    pub rng: fastrand::Rng,
+    pub control: Option<ControlSignal>,
+    /// Stop once the clock reaches this time
+    pub time_limit: Option<Duration>,
+    /// Stop once this many input events have been emitted
+    pub event_limit: Option<usize>,
+    /// Stop once this many actions have run, also catches loops that
+    /// neither emit events nor let time pass
+    pub action_limit: Option<usize>,
+    /// Every emitted event is logged here if set
+    pub timeline: Option<Vec<TimelineEntry>>,
+    /// The current position is published here if set
+    pub progress: Option<Progress>,
+    /// Variables set by the macro so far
+    pub variables: HashMap<String, Value>,
+    frames: Vec<LoopFrame>,
+    /// What the frame at the same index belongs to
+    frame_kinds: Vec<FrameKind>,
+    /// Set by a break or continue until the loop it targets, given by
+    /// its index in `frames`, has seen it
+    pending_jump: Option<(Jump, usize)>,
+    /// Procedures of the running loops together with the loop index of
+    /// their body, later ones shadow earlier ones with the same name
+    procedures: Vec<(Procedure, u32)>,
+    /// The file of the macro followed by the files it is including right now
+    files: Vec<PathBuf>,
+    events_emitted: usize,
+    actions_run: usize,
+    held: Vec<KeyButton>,
+    /// Number of enclosing loops when the macro was last paused
+    paused_depth: usize,
+}
+
+impl<'a> ExecutionContext<'a> {
+    pub fn new(backend: &'a mut dyn InputBackend, clock: &'a dyn Clock) -> Self {
+        Self {
+            backend,
+            clock,
+            screen: &NoScreen,
+            rng: fastrand::Rng::new(),
+            control: None,
+            time_limit: None,
+            event_limit: None,
+            action_limit: None,
+            timeline: None,
+            progress: None,
+            variables: HashMap::new(),
+            frames: Vec::new(),
+            frame_kinds: Vec::new(),
+            pending_jump: None,
+            procedures: Vec::new(),
+            files: Vec::new(),
+            events_emitted: 0,
+            actions_run: 0,
+            held: Vec::new(),
+            paused_depth: 0,
+        }
+    }
+
+    pub fn with_screen(mut self, screen: &'a dyn Screen) -> Self {
+        self.screen = screen;
+        self
+    }
+
+    pub fn with_control(mut self, control: ControlSignal) -> Self {
+        self.control = Some(control);
+        self
+    }
+
+    /// Makes all random choices reproducible
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = fastrand::Rng::with_seed(seed);
+        self
+    }
+
+    pub fn with_timeline(mut self) -> Self {
+        self.timeline = Some(Vec::new());
+        self
+    }
+
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// The file the macro was loaded from, includes are relative to it.
+    /// Without one they are relative to the working directory.
+    pub fn with_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        let path = path.as_ref();
+        self.files = vec![path.canonicalize().unwrap_or_else(|_| path.to_path_buf())];
+        self
+    }
+
+    /// The enclosing loops of the current action, outermost first
+    pub fn frames(&self) -> &[LoopFrame] {
+        &self.frames
+    }
+
+    /// Label of the current action like the editor shows it, e.g. `1.3`
+    pub fn path(&self) -> String {
+        match self.frames.last() {
+            Some(frame) => format!("{}.{}", frame.loop_index, frame.action),
+            None => String::new(),
+        }
+    }
+
+    /// Whether `time_limit`, `event_limit` or `action_limit` has been reached
+    pub fn limit_reached(&self) -> bool {
+        self.time_limit
+            .is_some_and(|limit| self.clock.now() >= limit)
+            || self
+                .event_limit
+                .is_some_and(|limit| self.events_emitted >= limit)
+            || self
+                .action_limit
+                .is_some_and(|limit| self.actions_run >= limit)
+    }
+
+    /// Sends `event` to the backend
+    pub fn emit(&mut self, event: InputEvent) -> Result<(), AppError> {
+        match event {
+            InputEvent::Key(key, direction) => self.backend.key(key, direction)?,
+            InputEvent::Button(button, direction) => self.backend.button(button, direction)?,
+            InputEvent::MoveMouse(x, y, coordinate) => self.backend.move_mouse(x, y, coordinate)?,
+            InputEvent::Text(c) => self.backend.text(c.encode_utf8(&mut [0; 4]))?,
+            InputEvent::Scroll(length, axis) => self.backend.scroll(length, axis)?,
+        }
+        self.events_emitted += 1;
+        self.track_held(event);
+        if self.timeline.is_some() {
+            let entry = TimelineEntry {
+                at: self.clock.now(),
+                path: self.path(),
+                event,
+                location: self.backend.location()?,
+            };
+            if let Some(timeline) = &mut self.timeline {
+                timeline.push(entry);
+            }
+        }
+        Ok(())
+    }
+
+    /// Value of the variable `name`. `i` and `iteration` are the
+    /// iteration of the innermost loop, counting from 0.
+    pub fn variable(&self, name: &str) -> Option<Value> {
+        match name {
+            "i" | "iteration" => {
+                let innermost_loop = self.find_loop("")?;
+                Some(Value::Int(self.frames[innermost_loop].iteration as i64))
+            }
+            _ => self.variables.get(name).cloned(),
+        }
+    }
+
+    pub fn set_variable(&mut self, name: &str, value: Value) -> Result<(), AppError> {
+        if matches!(name, "i" | "iteration") {
+            return Err(AppError::Expression(format!(
+                "`{}` is the loop iteration and can't be set",
+                name
+            )));
+        }
+        self.variables.insert(name.to_string(), value);
+        Ok(())
+    }
+
+    /// Evaluates the expression `source` with the current variables
+    pub fn eval(&self, source: &str) -> Result<Value, AppError> {
+        Expr::parse(source)?.eval(&|name| self.variable(name))
+    }
+
+    /// Picks the value of `param` for one run of an action
+    pub fn resolve<T: Number>(&mut self, param: &Param<T>) -> Result<T, AppError> {
+        match param {
+            Param::Ranged(ranged) => Ok(ranged.sample(&mut self.rng)),
+            Param::Expr(source) => {
+                let value = self.eval(source)?.as_int()?;
+                T::from_i64(value).ok_or_else(|| {
+                    AppError::Expression(format!(
+                        "`{}` is {}, which is out of range",
+                        source, value
+                    ))
+                })
+            }
+        }
+    }
+
+    fn enter_loop(&mut self, loop_index: u32, kind: FrameKind) {
+        self.frames.push(LoopFrame {
+            loop_index,
+            action: 0,
+            iteration: 0,
+        });
+        self.frame_kinds.push(kind);
+        self.publish_progress();
+    }
+
+    fn leave_loop(&mut self) {
+        self.frames.pop();
+        self.frame_kinds.pop();
+        self.publish_progress();
+    }
+
+    /// Index in `frames` of the innermost loop called `name`,
+    /// or of the innermost loop at all if `name` is empty
+    fn find_loop(&self, name: &str) -> Option<usize> {
+        for (depth, kind) in self.frame_kinds.iter().enumerate().rev() {
+            match kind {
+                FrameKind::Loop { name: loop_name } if name.is_empty() || loop_name == name => {
+                    return Some(depth)
+                }
+                FrameKind::Loop { .. } | FrameKind::Branch => {}
+                // the loops of the caller are out of reach
+                FrameKind::Body { name: loop_name } | FrameKind::Included { name: loop_name } => {
+                    return (name.is_empty() || loop_name == name).then_some(depth)
+                }
+            }
+        }
+        None
+    }
+
+    /// The innermost procedure called `name` and the loop index of its body
+    fn procedure(&self, name: &str) -> Option<(Procedure, u32)> {
+        self.procedures
+            .iter()
+            .rev()
+            .find(|(procedure, _)| procedure.name == name)
+            .cloned()
+    }
+
+    fn call_depth(&self) -> usize {
+        self.frame_kinds
+            .iter()
+            .filter(|kind| matches!(kind, FrameKind::Body { .. }))
+            .count()
+    }
+
+    fn set_position(&mut self, action: usize, iteration: u64) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.action = action;
+            frame.iteration = iteration;
+        }
+        self.publish_progress();
+    }
+
+    fn publish_progress(&self) {
+        if let Some(progress) = &self.progress {
+            // the editor doesn't show included files, their include stays highlighted
+            let shown = self
+                .frame_kinds
+                .iter()
+                .position(|kind| matches!(kind, FrameKind::Included { .. }))
+                .unwrap_or(self.frames.len());
+            progress.set(&self.frames[..shown]);
+        }
+    }
+
+    /// Keys and buttons that were pressed and not released yet
+    pub fn held(&self) -> &[KeyButton] {
+        &self.held
+    }
+
+    /// Releases everything that is still held down, most recent first.
+    /// Keeps going if a release fails and returns the first error.
+    pub fn release_held(&mut self) -> Result<(), AppError> {
+        let mut result = Ok(());
+        while let Some(keybutton) = self.held.pop() {
+            if let Err(err) = self.emit(keybutton.event(Direction::Release)) {
+                result = result.and(Err(err));
+            }
+        }
+        result
+    }
+
+    fn track_held(&mut self, event: InputEvent) {
+        let (keybutton, direction): (KeyButton, _) = match event {
+            InputEvent::Key(key, direction) => (key.into(), direction),
+            InputEvent::Button(button, direction) => (button.into(), direction),
+            InputEvent::MoveMouse(..) | InputEvent::Text(_) | InputEvent::Scroll(..) => return,
+        };
+        match direction {
+            Direction::Press => {
+                if !self.held.contains(&keybutton) {
+                    self.held.push(keybutton);
+                }
+            }
+            Direction::Release => self.held.retain(|held| *held != keybutton),
+            Direction::Click => {}
+        }
+    }
+
+    /// Waits on the clock. Returns early if the macro is stopped meanwhile,
+    /// time spent paused does not count towards the wait.
+    fn sleep_ms(&mut self, ms: u64) {
+        let Some(control) = &self.control else {
+            self.clock.sleep(Duration::from_millis(ms));
+            return;
+        };
+        let mut remaining = Duration::from_millis(ms);
+        loop {
+            let start = self.clock.now();
+            let interrupted = self.clock.sleep_interruptible(remaining, control);
+            remaining = remaining.saturating_sub(self.clock.now() - start);
+            if !interrupted || control.is_stopped() {
+                break;
+            }
+            self.paused_depth = self.frames.len();
+            control.wait_while_paused();
+            if remaining.is_zero() {
+                break;
+            }
+        }
+    }
+
+    /// Blocks between two actions while the macro is paused.
+    /// Returns whether it was paused.
+    fn wait_while_paused(&mut self) -> bool {
+        match &self.control {
+            Some(control) if control.is_paused() => {
+                self.paused_depth = self.frames.len();
+                control.wait_while_paused();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Pauses before the current action if it has a breakpoint
+    /// or a step ends here, then waits until it is resumed.
+    /// Returns whether it was paused.
+    fn break_if_needed(&mut self, breakpoint: bool) -> bool {
+        let Some(control) = &self.control else {
+            return false;
+        };
+        let step_done = match control.current_step() {
+            Some(Step::Into) => true,
+            Some(Step::Over) => self.frames.len() <= self.paused_depth,
+            None => false,
+        };
+        if breakpoint || step_done {
+            control.pause();
+        }
+        self.wait_while_paused()
+    }
+
+    fn should_stop(&self) -> bool {
+        self.control.as_ref().is_some_and(ControlSignal::is_stopped) || self.limit_reached()
+    }
+}
+
+enum FrameKind {
+    Loop {
+        name: String,
+    },
+    /// A branch of an if or a wait, it runs once and is not a target of
+    /// break or continue
+    Branch,
+    /// The body of a called procedure, jumps can't leave it
+    Body {
+        name: String,
+    },
+    /// The root loop of an included file, jumps can't leave it either
+    Included {
+        name: String,
+    },
+}
+
+/// Calls nested deeper than this fail instead of overflowing the stack
+const MAX_CALL_DEPTH: usize = 64;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Jump {
+    Break,
+    Continue,
+}
 
 #[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum KeyButton {
@@ -38,52 +435,318 @@ impl std::fmt::Display for KeyButton {
     }
 }
 impl KeyButton {
-    pub fn down(self, enigo: &mut Enigo) -> Result<(), AppError> {
+    pub fn event(self, direction: Direction) -> InputEvent {
         match self {
-            KeyButton::KeyboardKey(key) => enigo.key(key, Direction::Press)?,
-            KeyButton::MouseButton(button) => enigo.button(button, Direction::Press)?,
+            KeyButton::KeyboardKey(key) => InputEvent::Key(key, direction),
+            KeyButton::MouseButton(button) => InputEvent::Button(button, direction),
         }
-        Ok(())
-    }
-    pub fn up(self, enigo: &mut Enigo) -> Result<(), AppError> {
-        match self {
-            KeyButton::KeyboardKey(key) => enigo.key(key, Direction::Release)?,
-            KeyButton::MouseButton(button) => enigo.button(button, Direction::Release)?,
-        }
-        Ok(())
     }
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PressAction {
     pub keybutton: KeyButton,
     pub down: bool,
     pub up: bool,
-    pub hold_time_ms: u64,
-    pub delay_after_ms: u64,
+    pub hold_time_ms: Param<u64>,
+    pub delay_after_ms: Param<u64>,
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MoveAction {
-    pub x: i32,
-    pub y: i32,
+    pub x: Param<i32>,
+    pub y: Param<i32>,
     pub relative: bool,
-    pub move_time_ms: u64,
-    pub delay_after_ms: u64,
+    pub move_time_ms: Param<u64>,
+    pub delay_after_ms: Param<u64>,
+    /// Shape of a timed move, older files move in a straight line
+    #[serde(default)]
+    pub path: MovePath,
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MovePath {
+    pub shape: PathShape,
+    /// Every step of the move is randomly off the path by up to this many pixels,
+    /// the end point stays exact
+    pub jitter_px: u32,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PathShape {
+    /// Straight line at constant speed
+    #[default]
+    Linear,
+    /// Straight line, speeds up and slows down
+    EaseInOut,
+    /// Cubic Bézier curve with random control points
+    Bezier,
+    /// Moves past the target and comes back
+    Overshoot,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DelayAction {
     pub random: bool,
-    pub delay_ms_min: u64, // used if not random
-    pub delay_ms_max: u64,
+    pub delay_ms_min: Param<u64>, // used if not random
+    pub delay_ms_max: Param<u64>,
+}
+
+/// Sets the variable `name` to the result of the expression `value`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetVariableAction {
+    pub name: String,
+    pub value: String,
+}
+
+/// Leaves or skips to the next iteration of an enclosing loop
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoopJump {
+    /// Name of the loop, the innermost one if empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub target: String,
+    /// Only jumps if this is met
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<Condition>,
+}
+
+/// A named sub-macro that `CallAction` runs. The parameters are set as
+/// variables for the time of the call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Procedure {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub params: Vec<String>,
+    pub body: LoopAction,
+}
+
+/// Runs the procedure `name` with one expression per parameter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallAction {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+}
+
+/// Runs the macro saved in another file as if its root loop was nested here
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncludeAction {
+    /// Relative to the file of the including macro
+    pub path: String,
+}
+
+/// Adds `by` to the integer variable `name`, a new variable starts at 0
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncrementAction {
+    pub name: String,
+    pub by: i64,
+}
+
+/// How a timed move speeds up and slows down
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+/// Presses `button` at the start point, moves to the end point and releases it
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct DragAction {
+    pub start_x: Ranged<i32>,
+    pub start_y: Ranged<i32>,
+    pub end_x: Ranged<i32>,
+    pub end_y: Ranged<i32>,
+    pub button: enigo::Button,
+    pub duration_ms: Ranged<u64>,
+    pub easing: Easing,
+    pub delay_after_ms: Ranged<u64>,
+}
+
+/// Moves to a position and clicks there
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct ClickAtAction {
+    pub x: i32,
+    pub y: i32,
+    pub button: enigo::Button,
+    /// 2 for a double click, 3 for a triple click
+    pub clicks: u32,
+    pub click_interval_ms: u64,
+    /// The position is picked randomly within this distance of `x`, `y`
+    pub jitter_radius: u32,
+    pub delay_after_ms: Ranged<u64>,
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct ScrollAction {
+    pub axis: Axis,
+    /// Notches to scroll, positive values scroll down or right
+    pub amount: i32,
+    /// Spreads the notches evenly over this time, 0 scrolls all at once
+    pub scroll_time_ms: u64,
+    pub delay_after_ms: Ranged<u64>,
+}
+
+/// Presses `modifiers` in order and then `key`, releases them in reverse
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChordAction {
+    pub modifiers: Vec<KeyButton>,
+    pub key: KeyButton,
+    pub hold_time_ms: Ranged<u64>,
+    pub delay_after_ms: Ranged<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextAction {
+    pub text: String,
+    /// Pause between two characters
+    pub char_delay_ms: u64,
+    /// Up to this much is randomly added to every pause between characters
+    pub jitter_ms: u64,
+    pub delay_after_ms: Ranged<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoopAction {
+    /// Lets a break or continue in a nested loop refer to this one
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
     pub infinite: bool,
-    pub iterations: u64,
+    /// Evaluated once when the loop starts
+    pub iterations: Param<u64>,
     pub actions: Vec<Action>,
+    /// Indices of the actions the executor pauses at
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breakpoints: Vec<usize>,
+    /// Ends the loop once the time is up, checked before every iteration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_limit: Option<TimeLimit>,
+    /// Sub-macros the actions of this loop can call, the editor keeps
+    /// them on the root loop
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub procedures: Vec<Procedure>,
+}
+
+/// Runs `then_branch` if `condition` is met when the action starts,
+/// `else_branch` otherwise. Both branches run once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IfAction {
+    pub condition: Condition,
+    pub then_branch: LoopAction,
+    pub else_branch: LoopAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Condition {
+    /// The color at `x`, `y` is `color`, no channel off by more than `tolerance`.
+    /// With a `radius` the average of the square around the point is compared.
+    PixelColor {
+        x: i32,
+        y: i32,
+        radius: u32,
+        color: Color,
+        tolerance: u8,
+    },
+    /// The cursor is inside the rectangle with its top left corner at `x`, `y`
+    CursorIn {
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    },
+    /// The macro has been running for at least `ms`
+    Elapsed { ms: u64 },
+    /// The expression, e.g. `iteration % 10 == 0`, is `true`
+    Expression(String),
+}
+
+/// Waits until `condition` is met, checking it every `poll_interval_ms`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaitUntilAction {
+    pub condition: Condition,
+    pub poll_interval_ms: u64,
+    /// Gives up after waiting this long, 0 waits forever
+    pub timeout_ms: u64,
+    pub on_timeout: OnTimeout,
+    /// Runs once if the wait times out and `on_timeout` is `RunBranch`
+    pub timeout_branch: LoopAction,
+}
+
+/// What happens when a `WaitUntilAction` gives up
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OnTimeout {
+    /// Goes on with the next action
+    #[default]
+    Continue,
+    /// Ends the macro with an error
+    Stop,
+    /// Runs the timeout branch, then goes on with the next action
+    RunBranch,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeLimit {
+    /// Runs for this long after the loop started
+    For { secs: u64 },
+    /// Runs until the next time the clock shows this time of day.
+    /// The time is in the local time zone of the system unless
+    /// `utc_offset_minutes` fixes one that many minutes ahead of UTC.
+    Until {
+        hour: u8,
+        minute: u8,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        utc_offset_minutes: Option<i16>,
+    },
+}
+
+impl TimeLimit {
+    /// How long a loop starting now may run
+    pub fn remaining(&self, clock: &dyn Clock) -> Duration {
+        match *self {
+            TimeLimit::For { secs } => Duration::from_secs(secs),
+            TimeLimit::Until {
+                hour,
+                minute,
+                utc_offset_minutes: None,
+            } => until_local(hour, minute, clock.wall_time()),
+            TimeLimit::Until {
+                hour,
+                minute,
+                utc_offset_minutes: Some(utc_offset_minutes),
+            } => {
+                const DAY: i64 = 24 * 60 * 60;
+                let since_epoch = clock
+                    .wall_time()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default();
+                let local_secs = since_epoch.as_secs() as i64 + i64::from(utc_offset_minutes) * 60;
+                let target_secs = i64::from(hour) * 60 * 60 + i64::from(minute) * 60;
+                let wait_secs = (target_secs - local_secs).rem_euclid(DAY);
+                Duration::from_secs(wait_secs as u64)
+                    .saturating_sub(Duration::from_nanos(since_epoch.subsec_nanos().into()))
+            }
+        }
+    }
+}
+
+/// Time from `now` until the local clock next shows `hour`:`minute`.
+/// Follows daylight saving time, a time skipped by it is taken from the next day.
+fn until_local(hour: u8, minute: u8, now: SystemTime) -> Duration {
+    let now = DateTime::<Local>::from(now);
+    let time =
+        NaiveTime::from_hms_opt(hour.min(23).into(), minute.min(59).into(), 0).unwrap_or_default();
+    // like with a fixed offset, the current second still counts as reached
+    let second = now.trunc_subsecs(0);
+    (0..3)
+        .filter_map(|days| {
+            let date = now.date_naive() + chrono::Days::new(days);
+            date.and_time(time).and_local_timezone(Local).earliest()
+        })
+        .find(|target| *target >= second)
+        .and_then(|target| (target - now).to_std().ok())
+        .unwrap_or_default()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,6 +755,19 @@ pub enum Action {
     Press(PressAction),
     Move(MoveAction),
     Delay(DelayAction),
+    Text(TextAction),
+    Chord(ChordAction),
+    Scroll(ScrollAction),
+    ClickAt(ClickAtAction),
+    Drag(DragAction),
+    If(IfAction),
+    WaitUntil(WaitUntilAction),
+    SetVariable(SetVariableAction),
+    Increment(IncrementAction),
+    Break(LoopJump),
+    Continue(LoopJump),
+    Call(CallAction),
+    Include(IncludeAction),
 }
 impl From<MoveAction> for Action {
     fn from(value: MoveAction) -> Self {
@@ -108,6 +784,61 @@ impl From<DelayAction> for Action {
         Action::Delay(value)
     }
 }
+impl From<TextAction> for Action {
+    fn from(value: TextAction) -> Self {
+        Action::Text(value)
+    }
+}
+impl From<ChordAction> for Action {
+    fn from(value: ChordAction) -> Self {
+        Action::Chord(value)
+    }
+}
+impl From<ScrollAction> for Action {
+    fn from(value: ScrollAction) -> Self {
+        Action::Scroll(value)
+    }
+}
+impl From<ClickAtAction> for Action {
+    fn from(value: ClickAtAction) -> Self {
+        Action::ClickAt(value)
+    }
+}
+impl From<DragAction> for Action {
+    fn from(value: DragAction) -> Self {
+        Action::Drag(value)
+    }
+}
+impl From<IfAction> for Action {
+    fn from(value: IfAction) -> Self {
+        Action::If(value)
+    }
+}
+impl From<WaitUntilAction> for Action {
+    fn from(value: WaitUntilAction) -> Self {
+        Action::WaitUntil(value)
+    }
+}
+impl From<SetVariableAction> for Action {
+    fn from(value: SetVariableAction) -> Self {
+        Action::SetVariable(value)
+    }
+}
+impl From<CallAction> for Action {
+    fn from(value: CallAction) -> Self {
+        Action::Call(value)
+    }
+}
+impl From<IncludeAction> for Action {
+    fn from(value: IncludeAction) -> Self {
+        Action::Include(value)
+    }
+}
+impl From<IncrementAction> for Action {
+    fn from(value: IncrementAction) -> Self {
+        Action::Increment(value)
+    }
+}
 impl From<LoopAction> for Action {
     fn from(value: LoopAction) -> Self {
         Action::Loop(value)
@@ -115,153 +846,717 @@ impl From<LoopAction> for Action {
 }
 
 impl MoveAction {
-    pub fn execute(self, enigo: &mut Enigo) -> Result<(), AppError> {
-        if self.move_time_ms == 0 {
+    pub fn execute(self, ctx: &mut ExecutionContext) -> Result<(), AppError> {
+        let target_x = ctx.resolve(&self.x)?;
+        let target_y = ctx.resolve(&self.y)?;
+        let move_time_ms = ctx.resolve(&self.move_time_ms)?;
+        if move_time_ms == 0 {
             if self.relative {
-                enigo.move_mouse(self.x, self.y, Coordinate::Rel)?;
+                ctx.emit(InputEvent::MoveMouse(target_x, target_y, Coordinate::Rel))?;
             } else {
-                enigo.move_mouse(self.x, self.y, Coordinate::Abs)?;
+                ctx.emit(InputEvent::MoveMouse(target_x, target_y, Coordinate::Abs))?;
             }
         } else {
-            let timestep_ms = 3;
-            let mut x_rel = self.x;
-            let mut y_rel = self.y;
+            let mut x_rel = target_x;
+            let mut y_rel = target_y;
 
             if !self.relative {
-                let pos = enigo.location()?;
-                x_rel = self.x - pos.0;
-                y_rel = self.y - pos.1;
+                let pos = ctx.backend.location()?;
+                x_rel = target_x - pos.0;
+                y_rel = target_y - pos.1;
             }
 
-            let mut time_passed_ms: u64 = 0;
-            let mut x_last_cycle = 0;
-            let mut y_last_cycle = 0;
+            let curve = self.path.shape.curve(x_rel, y_rel, &mut ctx.rng);
+            interpolate_move(ctx, move_time_ms, curve, self.path.jitter_px)?;
+        }
 
-            loop {
-                let factor = time_passed_ms as f64 / self.move_time_ms as f64;
-                let x = (x_rel as f64 * factor).floor() as i32;
-                let y = (y_rel as f64 * factor).floor() as i32;
+        let delay_after_ms = ctx.resolve(&self.delay_after_ms)?;
+        ctx.sleep_ms(delay_after_ms);
+        Ok(())
+    }
+}
 
-                enigo.move_mouse(x - x_last_cycle, y - y_last_cycle, Coordinate::Rel)?;
+/// Moves the cursor along `curve` in small relative steps spread over
+/// `move_time_ms`. `curve` maps the elapsed fraction of the time to the
+/// offset from the start point and has to end at the target.
+fn interpolate_move(
+    ctx: &mut ExecutionContext,
+    move_time_ms: u64,
+    curve: impl Fn(f64) -> (f64, f64),
+    jitter_px: u32,
+) -> Result<(), AppError> {
+    let timestep_ms = 3;
+    let mut time_passed_ms: u64 = 0;
+    let mut x_last_cycle = 0;
+    let mut y_last_cycle = 0;
 
-                let sleep_time = if time_passed_ms + timestep_ms < self.move_time_ms {
-                    timestep_ms
-                } else {
-                    self.move_time_ms - time_passed_ms
-                };
+    loop {
+        let (x, y) = curve(time_passed_ms as f64 / move_time_ms as f64);
+        let mut x = x.floor() as i32;
+        let mut y = y.floor() as i32;
+        if jitter_px > 0 && time_passed_ms > 0 && time_passed_ms < move_time_ms {
+            let jitter = jitter_px as i32;
+            x += ctx.rng.i32(-jitter..=jitter);
+            y += ctx.rng.i32(-jitter..=jitter);
+        }
 
-                if time_passed_ms >= self.move_time_ms {
-                    break;
+        ctx.emit(InputEvent::MoveMouse(
+            x - x_last_cycle,
+            y - y_last_cycle,
+            Coordinate::Rel,
+        ))?;
+
+        let sleep_time = if time_passed_ms + timestep_ms < move_time_ms {
+            timestep_ms
+        } else {
+            move_time_ms - time_passed_ms
+        };
+
+        if time_passed_ms >= move_time_ms {
+            break;
+        }
+        ctx.sleep_ms(sleep_time);
+        if ctx.should_stop() {
+            break;
+        }
+        time_passed_ms += sleep_time;
+        x_last_cycle = x;
+        y_last_cycle = y;
+    }
+    Ok(())
+}
+
+impl PathShape {
+    /// Offset from the start point after the fraction `t` of the move to `x_rel`, `y_rel`
+    fn curve(self, x_rel: i32, y_rel: i32, rng: &mut fastrand::Rng) -> impl Fn(f64) -> (f64, f64) {
+        let (dx, dy) = (x_rel as f64, y_rel as f64);
+        // control points of the Bézier curve, pushed sideways by up to
+        // a third of the distance
+        let mut bend = || (rng.f64() - 0.5) * 2.0 / 3.0;
+        let (bend1, bend2) = (bend(), bend());
+        let control1 = (dx / 3.0 - dy * bend1, dy / 3.0 + dx * bend1);
+        let control2 = (dx * 2.0 / 3.0 - dy * bend2, dy * 2.0 / 3.0 + dx * bend2);
+
+        move |t| {
+            let t = t.clamp(0.0, 1.0);
+            match self {
+                PathShape::Linear => (dx * t, dy * t),
+                PathShape::EaseInOut => {
+                    let f = Easing::EaseInOut.apply(t);
+                    (dx * f, dy * f)
+                }
+                PathShape::Bezier => {
+                    let t = Easing::EaseInOut.apply(t);
+                    let u = 1.0 - t;
+                    let (a, b, c) = (3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+                    (
+                        a * control1.0 + b * control2.0 + c * dx,
+                        a * control1.1 + b * control2.1 + c * dy,
+                    )
+                }
+                PathShape::Overshoot => {
+                    // ease-out-back, goes about 10% past the target
+                    let c1 = 1.70158;
+                    let c3 = c1 + 1.0;
+                    let f = 1.0 + c3 * (t - 1.0).powi(3) + c1 * (t - 1.0).powi(2);
+                    (dx * f, dy * f)
                 }
-                thread::sleep(time::Duration::from_millis(sleep_time));
-                time_passed_ms += sleep_time;
-                x_last_cycle = x;
-                y_last_cycle = y;
             }
         }
+    }
+}
 
-        thread::sleep(time::Duration::from_millis(self.delay_after_ms));
+impl Easing {
+    /// Maps the elapsed fraction of the time to the covered fraction of the way
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+impl DragAction {
+    /// The button is released at the end even if the move fails or the macro is stopped
+    pub fn execute(self, ctx: &mut ExecutionContext) -> Result<(), AppError> {
+        let start_x = self.start_x.sample(&mut ctx.rng);
+        let start_y = self.start_y.sample(&mut ctx.rng);
+        let end_x = self.end_x.sample(&mut ctx.rng);
+        let end_y = self.end_y.sample(&mut ctx.rng);
+        let duration_ms = self.duration_ms.sample(&mut ctx.rng);
+        ctx.emit(InputEvent::MoveMouse(start_x, start_y, Coordinate::Abs))?;
+        ctx.emit(InputEvent::Button(self.button, Direction::Press))?;
+        let moved = if duration_ms == 0 {
+            ctx.emit(InputEvent::MoveMouse(end_x, end_y, Coordinate::Abs))
+        } else {
+            let (x_rel, y_rel) = ((end_x - start_x) as f64, (end_y - start_y) as f64);
+            let easing = self.easing;
+            let curve = move |t| (x_rel * easing.apply(t), y_rel * easing.apply(t));
+            interpolate_move(ctx, duration_ms, curve, 0)
+        };
+        let released = ctx.emit(InputEvent::Button(self.button, Direction::Release));
+        moved.and(released)?;
+        let delay_after_ms = self.delay_after_ms.sample(&mut ctx.rng);
+        ctx.sleep_ms(delay_after_ms);
         Ok(())
     }
 }
 
 impl PressAction {
-    pub fn execute(self, enigo: &mut Enigo) -> Result<(), AppError> {
+    pub fn execute(self, ctx: &mut ExecutionContext) -> Result<(), AppError> {
         if self.down {
-            self.keybutton.down(enigo)?;
+            ctx.emit(self.keybutton.event(Direction::Press))?;
         } else if self.up {
-            self.keybutton.up(enigo)?;
+            ctx.emit(self.keybutton.event(Direction::Release))?;
         }
         if self.down && self.up {
-            thread::sleep(time::Duration::from_millis(self.hold_time_ms));
-            self.keybutton.up(enigo)?;
+            let hold_time_ms = ctx.resolve(&self.hold_time_ms)?;
+            ctx.sleep_ms(hold_time_ms);
+            ctx.emit(self.keybutton.event(Direction::Release))?;
         }
-        thread::sleep(time::Duration::from_millis(self.delay_after_ms));
+        let delay_after_ms = ctx.resolve(&self.delay_after_ms)?;
+        ctx.sleep_ms(delay_after_ms);
         Ok(())
     }
 }
 
 impl DelayAction {
-    pub fn execute(self) {
-        if !self.random || self.delay_ms_min >= self.delay_ms_max {
-            thread::sleep(time::Duration::from_millis(self.delay_ms_min));
+    pub fn execute(self, ctx: &mut ExecutionContext) -> Result<(), AppError> {
+        let delay_ms_min = ctx.resolve(&self.delay_ms_min)?;
+        let delay_ms_max = if self.random {
+            ctx.resolve(&self.delay_ms_max)?
+        } else {
+            0
+        };
+        if !self.random || delay_ms_min >= delay_ms_max {
+            ctx.sleep_ms(delay_ms_min);
         } else {
-            thread::sleep(time::Duration::from_millis(fastrand::u64(
-                self.delay_ms_min..self.delay_ms_max,
+            let delay_ms = ctx.rng.u64(delay_ms_min..delay_ms_max);
+            ctx.sleep_ms(delay_ms);
+        }
+        Ok(())
+    }
+}
+
+impl SetVariableAction {
+    pub fn execute(self, ctx: &mut ExecutionContext) -> Result<(), AppError> {
+        let value = ctx.eval(&self.value)?;
+        ctx.set_variable(&self.name, value)
+    }
+}
+
+impl LoopJump {
+    fn execute(self, ctx: &mut ExecutionContext, jump: Jump) -> Result<(), AppError> {
+        if let Some(condition) = &self.condition {
+            if !condition.is_met(ctx)? {
+                return Ok(());
+            }
+        }
+        let depth = ctx
+            .find_loop(&self.target)
+            .ok_or_else(|| AppError::NoSuchLoop(self.target.clone()))?;
+        ctx.pending_jump = Some((jump, depth));
+        Ok(())
+    }
+}
+
+impl CallAction {
+    fn execute(self, ctx: &mut ExecutionContext) -> Result<(), AppError> {
+        let (procedure, loop_index) = ctx
+            .procedure(&self.name)
+            .ok_or_else(|| AppError::Procedure(format!("unknown procedure `{}`", self.name)))?;
+        if self.args.len() != procedure.params.len() {
+            return Err(AppError::Procedure(format!(
+                "`{}` takes {} arguments but got {}",
+                self.name,
+                procedure.params.len(),
+                self.args.len()
             )));
         }
+        if ctx.call_depth() >= MAX_CALL_DEPTH {
+            return Err(AppError::Procedure(format!(
+                "calls are nested deeper than {}",
+                MAX_CALL_DEPTH
+            )));
+        }
+        // all arguments see the variables of the caller
+        let values = self
+            .args
+            .iter()
+            .map(|arg| ctx.eval(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        let shadowed: Vec<_> = procedure
+            .params
+            .iter()
+            .map(|param| (param.clone(), ctx.variables.get(param).cloned()))
+            .collect();
+        let result = procedure
+            .params
+            .iter()
+            .zip(values)
+            .try_for_each(|(param, value)| ctx.set_variable(param, value))
+            .and_then(|()| {
+                let kind = FrameKind::Body {
+                    name: procedure.body.name.clone(),
+                };
+                procedure.body.execute_as(ctx, loop_index, kind)
+            });
+        for (param, value) in shadowed {
+            match value {
+                Some(value) => ctx.variables.insert(param, value),
+                None => ctx.variables.remove(&param),
+            };
+        }
+        result
+    }
+}
+
+impl IncludeAction {
+    fn execute(self, ctx: &mut ExecutionContext) -> Result<(), AppError> {
+        self.execute_file(ctx).map_err(|err| AppError::Include {
+            path: self.path.clone(),
+            source: Box::new(err),
+        })
+    }
+
+    fn execute_file(&self, ctx: &mut ExecutionContext) -> Result<(), AppError> {
+        let path = match ctx.files.last().and_then(|file| file.parent()) {
+            Some(dir) => dir.join(&self.path),
+            None => PathBuf::from(&self.path),
+        };
+        let path = path.canonicalize()?;
+        if ctx.files.contains(&path) {
+            return Err(AppError::IncludeCycle(path.display().to_string()));
+        }
+        let mut root = LoopAction::once(Vec::new());
+        root.load_from_disk(&path)?;
+
+        ctx.files.push(path);
+        // numbered on its own, errors in it are labeled like its editor would
+        let kind = FrameKind::Included {
+            name: root.name.clone(),
+        };
+        let result = root.execute_as(ctx, 1, kind);
+        ctx.files.pop();
+        result
+    }
+}
+
+impl IncrementAction {
+    pub fn execute(self, ctx: &mut ExecutionContext) -> Result<(), AppError> {
+        let current = match ctx.variables.get(&self.name) {
+            Some(value) => value.as_int()?,
+            None => 0,
+        };
+        let value = current
+            .checked_add(self.by)
+            .ok_or_else(|| AppError::Expression("number too large".to_string()))?;
+        ctx.set_variable(&self.name, Value::Int(value))
+    }
+}
+
+impl ClickAtAction {
+    pub fn execute(self, ctx: &mut ExecutionContext) -> Result<(), AppError> {
+        let (dx, dy) = random_offset(&mut ctx.rng, self.jitter_radius);
+        ctx.emit(InputEvent::MoveMouse(
+            self.x.saturating_add(dx),
+            self.y.saturating_add(dy),
+            Coordinate::Abs,
+        ))?;
+        for i in 0..self.clicks {
+            if i > 0 {
+                ctx.sleep_ms(self.click_interval_ms);
+                if ctx.should_stop() {
+                    break;
+                }
+            }
+            ctx.emit(InputEvent::Button(self.button, Direction::Press))?;
+            ctx.emit(InputEvent::Button(self.button, Direction::Release))?;
+        }
+        let delay_after_ms = self.delay_after_ms.sample(&mut ctx.rng);
+        ctx.sleep_ms(delay_after_ms);
+        Ok(())
+    }
+}
+
+/// Uniformly distributed point within `radius` of the origin
+fn random_offset(rng: &mut fastrand::Rng, radius: u32) -> (i32, i32) {
+    // the offsets fit into an i32 and the sum of their squares into an i64
+    let radius = i64::from(radius.min(i32::MAX as u32));
+    loop {
+        let dx = rng.i64(-radius..=radius);
+        let dy = rng.i64(-radius..=radius);
+        if dx * dx + dy * dy <= radius * radius {
+            return (dx as i32, dy as i32);
+        }
+    }
+}
+
+impl ScrollAction {
+    pub fn execute(self, ctx: &mut ExecutionContext) -> Result<(), AppError> {
+        let notches = self.amount.unsigned_abs() as u64;
+        if self.scroll_time_ms == 0 {
+            if self.amount != 0 {
+                ctx.emit(InputEvent::Scroll(self.amount, self.axis))?;
+            }
+        } else {
+            let step = self.amount.signum();
+            let mut time_passed_ms = 0;
+            for i in 1..=notches {
+                // sleep up to the time the notch is due, so the rounding does not add up
+                let due_ms = i * self.scroll_time_ms / notches;
+                ctx.sleep_ms(due_ms - time_passed_ms);
+                if ctx.should_stop() {
+                    break;
+                }
+                time_passed_ms = due_ms;
+                ctx.emit(InputEvent::Scroll(step, self.axis))?;
+            }
+        }
+        let delay_after_ms = self.delay_after_ms.sample(&mut ctx.rng);
+        ctx.sleep_ms(delay_after_ms);
+        Ok(())
+    }
+}
+
+impl ChordAction {
+    pub fn execute(self, ctx: &mut ExecutionContext) -> Result<(), AppError> {
+        let keys: Vec<KeyButton> = self.modifiers.iter().copied().chain([self.key]).collect();
+        for keybutton in &keys {
+            ctx.emit(keybutton.event(Direction::Press))?;
+        }
+        let hold_time_ms = self.hold_time_ms.sample(&mut ctx.rng);
+        ctx.sleep_ms(hold_time_ms);
+        for keybutton in keys.iter().rev() {
+            ctx.emit(keybutton.event(Direction::Release))?;
+        }
+        let delay_after_ms = self.delay_after_ms.sample(&mut ctx.rng);
+        ctx.sleep_ms(delay_after_ms);
+        Ok(())
+    }
+}
+
+impl TextAction {
+    pub fn execute(self, ctx: &mut ExecutionContext) -> Result<(), AppError> {
+        for (i, c) in self.text.chars().enumerate() {
+            if i > 0 {
+                let jitter = ctx.rng.u64(0..=self.jitter_ms);
+                ctx.sleep_ms(self.char_delay_ms + jitter);
+                if ctx.should_stop() {
+                    break;
+                }
+            }
+            ctx.emit(InputEvent::Text(c))?;
+        }
+        let delay_after_ms = self.delay_after_ms.sample(&mut ctx.rng);
+        ctx.sleep_ms(delay_after_ms);
+        Ok(())
+    }
+}
+
+impl Condition {
+    pub fn is_met(&self, ctx: &ExecutionContext) -> Result<bool, AppError> {
+        match *self {
+            Condition::PixelColor {
+                x,
+                y,
+                radius,
+                color,
+                tolerance,
+            } => Ok(ctx.screen.average(x, y, radius)?.matches(color, tolerance)),
+            Condition::CursorIn {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let (cursor_x, cursor_y) = ctx.backend.location()?;
+                // in i64, so `x + width` can't overflow
+                let within = |start: i32, len: u32, pos: i32| {
+                    (i64::from(start)..i64::from(start) + i64::from(len)).contains(&i64::from(pos))
+                };
+                Ok(within(x, width, cursor_x) && within(y, height, cursor_y))
+            }
+            Condition::Elapsed { ms } => Ok(ctx.clock.now() >= Duration::from_millis(ms)),
+            Condition::Expression(ref source) => ctx.eval(source)?.as_bool(),
+        }
+    }
+}
+
+impl WaitUntilAction {
+    pub fn new(condition: Condition) -> Self {
+        Self {
+            condition,
+            poll_interval_ms: 100,
+            timeout_ms: 10_000,
+            on_timeout: OnTimeout::Continue,
+            timeout_branch: LoopAction::once(Vec::new()),
+        }
+    }
+
+    /// Time spent paused does not count towards the timeout.
+    /// The timeout branch is numbered like a nested loop.
+    fn execute(self, ctx: &mut ExecutionContext, loop_index: u32) -> Result<(), AppError> {
+        let mut waited_ms = 0;
+        loop {
+            if self.condition.is_met(ctx)? {
+                return Ok(());
+            }
+            if self.timeout_ms > 0 && waited_ms >= self.timeout_ms {
+                break;
+            }
+            let mut sleep_ms = self.poll_interval_ms.max(1);
+            if self.timeout_ms > 0 {
+                sleep_ms = sleep_ms.min(self.timeout_ms - waited_ms);
+            }
+            ctx.sleep_ms(sleep_ms);
+            if ctx.should_stop() {
+                return Ok(());
+            }
+            waited_ms += sleep_ms;
+        }
+        match self.on_timeout {
+            OnTimeout::Continue => Ok(()),
+            OnTimeout::Stop => Err(AppError::Timeout(self.timeout_ms)),
+            OnTimeout::RunBranch => self.timeout_branch.execute_branch(ctx, loop_index),
+        }
+    }
+}
+
+impl IfAction {
+    pub fn new(condition: Condition) -> Self {
+        Self {
+            condition,
+            then_branch: LoopAction::once(Vec::new()),
+            else_branch: LoopAction::once(Vec::new()),
+        }
+    }
+
+    /// The branches are numbered like nested loops, `then_branch` first
+    fn execute(self, ctx: &mut ExecutionContext, loop_index: u32) -> Result<(), AppError> {
+        if self.condition.is_met(ctx)? {
+            self.then_branch.execute_branch(ctx, loop_index)
+        } else {
+            let loop_index = loop_index + self.then_branch.loop_count();
+            self.else_branch.execute_branch(ctx, loop_index)
+        }
+    }
+
+    pub fn loop_count(&self) -> u32 {
+        self.then_branch.loop_count() + self.else_branch.loop_count()
     }
 }
 
 impl LoopAction {
-    pub fn execute(
+    /// A loop running `actions` a single time, e.g. a branch
+    pub fn once(actions: Vec<Action>) -> Self {
+        Self {
+            name: String::new(),
+            infinite: false,
+            iterations: 1.into(),
+            actions,
+            breakpoints: Vec::new(),
+            time_limit: None,
+            procedures: Vec::new(),
+        }
+    }
+
+    /// Runs the loop as the root of a macro. Whatever is still held down
+    /// afterwards is released, also if the macro was stopped or failed.
+    pub fn execute(self, ctx: &mut ExecutionContext) -> Result<(), AppError> {
+        let result = self.execute_numbered(ctx, 1);
+        let released = ctx.release_held();
+        result.and(released)
+    }
+
+    fn execute_numbered(self, ctx: &mut ExecutionContext, loop_index: u32) -> Result<(), AppError> {
+        let kind = FrameKind::Loop {
+            name: self.name.clone(),
+        };
+        self.execute_as(ctx, loop_index, kind)
+    }
+
+    fn execute_as(
         self,
-        enigo: &mut Enigo,
-        stop_execution: Option<Arc<AtomicBool>>,
+        ctx: &mut ExecutionContext,
+        loop_index: u32,
+        kind: FrameKind,
+    ) -> Result<(), AppError> {
+        // evaluated outside of the loop, so `i` is the iteration of the enclosing one
+        let iterations = ctx.resolve(&self.iterations)?;
+        ctx.enter_loop(loop_index, kind);
+        let result = self.execute_frame(ctx, loop_index, iterations);
+        ctx.leave_loop();
+        result
+    }
+
+    /// Runs the actions once. Unlike in a nested loop, `i` keeps
+    /// counting the iterations of the enclosing loop.
+    fn execute_branch(self, ctx: &mut ExecutionContext, loop_index: u32) -> Result<(), AppError> {
+        ctx.enter_loop(loop_index, FrameKind::Branch);
+        let result = self.execute_frame(ctx, loop_index, 1);
+        ctx.leave_loop();
+        result
+    }
+
+    fn execute_frame(
+        &self,
+        ctx: &mut ExecutionContext,
+        loop_index: u32,
+        iterations: u64,
+    ) -> Result<(), AppError> {
+        let scope = ctx.procedures.len();
+        // bodies are numbered after the actions, like the editor draws them
+        let mut body_loop_index = loop_index + 1 + self.actions_loop_count();
+        for procedure in &self.procedures {
+            ctx.procedures.push((procedure.clone(), body_loop_index));
+            body_loop_index += procedure.body.loop_count();
+        }
+        let result = self.execute_iterations(ctx, loop_index, iterations);
+        ctx.procedures.truncate(scope);
+        result
+    }
+
+    fn execute_iterations(
+        &self,
+        ctx: &mut ExecutionContext,
+        loop_index: u32,
+        iterations: u64,
     ) -> Result<(), AppError> {
         let mut i = 0;
         let mut terminate = false;
+        let deadline = self
+            .time_limit
+            .map(|limit| ctx.clock.now() + limit.remaining(ctx.clock));
 
-        while (i < self.iterations || self.infinite) && !terminate {
-            for action in &self.actions {
-                action.clone().execute(enigo, stop_execution.clone())?;
-                if stop_execution
-                    .as_ref()
-                    .is_some_and(|b| b.load(Ordering::Relaxed))
-                {
+        while (i < iterations || self.infinite) && !terminate {
+            if deadline.is_some_and(|deadline| ctx.clock.now() >= deadline) {
+                break;
+            }
+            // nested loops are numbered in the same order the editor draws them
+            let mut child_loop_index = loop_index + 1;
+            for (pos, action) in self.actions.iter().enumerate() {
+                ctx.set_position(pos, i);
+                // the macro may have been stopped while it was paused here
+                if ctx.break_if_needed(self.breakpoints.contains(&pos)) && ctx.should_stop() {
                     terminate = true;
                     break;
                 }
+                ctx.actions_run += 1;
+                action
+                    .clone()
+                    .execute(ctx, child_loop_index)
+                    .map_err(|err| err.in_action(ctx.path()))?;
+                child_loop_index += action.loop_count();
+                ctx.wait_while_paused();
+                if ctx.should_stop() {
+                    terminate = true;
+                    break;
+                }
+                if let Some((jump, depth)) = ctx.pending_jump {
+                    // a jump out of an enclosing loop leaves this one as well
+                    if depth + 1 == ctx.frames.len() {
+                        ctx.pending_jump = None;
+                        terminate = jump == Jump::Break;
+                    } else {
+                        terminate = true;
+                    }
+                    break;
+                }
             }
-            if !self.infinite {
-                i = i + 1;
-            } else if self.actions.is_empty() {
+            i += 1;
+            if self.infinite && self.actions.is_empty() {
                 break;
             }
         }
         Ok(())
     }
 
-    pub fn save_to_disk<P: AsRef<Path>>(&self, path: &P) -> std::io::Result<()> {
+    /// Removes the action at `index` together with its breakpoint
+    pub fn remove_action(&mut self, index: usize) -> Action {
+        self.breakpoints.retain(|&pos| pos != index);
+        for pos in &mut self.breakpoints {
+            if *pos > index {
+                *pos -= 1;
+            }
+        }
+        self.actions.remove(index)
+    }
+
+    pub fn toggle_breakpoint(&mut self, index: usize) {
+        if let Some(i) = self.breakpoints.iter().position(|&pos| pos == index) {
+            self.breakpoints.remove(i);
+        } else {
+            self.breakpoints.push(index);
+        }
+    }
+
+    /// Number of loops in this tree, including this one and the bodies
+    /// of its procedures
+    pub fn loop_count(&self) -> u32 {
+        1 + self.actions_loop_count()
+            + self
+                .procedures
+                .iter()
+                .map(|procedure| procedure.body.loop_count())
+                .sum::<u32>()
+    }
+
+    fn actions_loop_count(&self) -> u32 {
+        self.actions.iter().map(Action::loop_count).sum()
+    }
+
+    pub fn save_to_disk<P: AsRef<Path>>(&self, path: &P) -> Result<(), AppError> {
         let mut f = File::create(path.as_ref())?;
-        let buf = serde_json::to_vec(&self)?;
+        let buf = serde_json::to_vec(&self).map_err(Error::from)?;
         f.write_all(&buf[..])?;
-        return Ok(());
+        Ok(())
     }
 
-    pub fn load_from_disk<P: AsRef<Path>>(&mut self, path: &P) -> std::io::Result<()> {
+    pub fn load_from_disk<P: AsRef<Path>>(&mut self, path: &P) -> Result<(), AppError> {
         let mut f = File::open(path.as_ref())?;
         let mut buf = vec![];
-        match f.read_to_end(&mut buf) {
-            Ok(_) => {
-                if let Ok(loopaction) = serde_json::from_slice::<LoopAction>(&buf[..]) {
-                    self.clone_from(&loopaction);
-                } else {
-                    return Err(Error::new(
-                        std::io::ErrorKind::Other,
-                        "Couldn't deserialize buf into a LoopAction",
-                    ));
-                }
-            }
-            Err(val) => return Err(val),
-        }
+        f.read_to_end(&mut buf)?;
+        let loopaction = serde_json::from_slice::<LoopAction>(&buf[..])?;
+        self.clone_from(&loopaction);
 
-        return Ok(());
+        Ok(())
     }
 }
 
 impl Action {
-    fn execute(
-        self,
-        enigo: &mut Enigo,
-        stop_execution: Option<Arc<AtomicBool>>,
-    ) -> Result<(), AppError> {
+    fn execute(self, ctx: &mut ExecutionContext, loop_index: u32) -> Result<(), AppError> {
         match self {
-            Action::Loop(val) => val.execute(enigo, stop_execution)?,
-            Action::Move(val) => val.execute(enigo)?,
-            Action::Press(val) => val.execute(enigo)?,
-            Action::Delay(val) => val.execute(),
+            Action::Loop(val) => val.execute_numbered(ctx, loop_index)?,
+            Action::Move(val) => val.execute(ctx)?,
+            Action::Press(val) => val.execute(ctx)?,
+            Action::Delay(val) => val.execute(ctx)?,
+            Action::Text(val) => val.execute(ctx)?,
+            Action::Chord(val) => val.execute(ctx)?,
+            Action::Scroll(val) => val.execute(ctx)?,
+            Action::ClickAt(val) => val.execute(ctx)?,
+            Action::Drag(val) => val.execute(ctx)?,
+            Action::If(val) => val.execute(ctx, loop_index)?,
+            Action::WaitUntil(val) => val.execute(ctx, loop_index)?,
+            Action::SetVariable(val) => val.execute(ctx)?,
+            Action::Increment(val) => val.execute(ctx)?,
+            Action::Break(val) => val.execute(ctx, Jump::Break)?,
+            Action::Continue(val) => val.execute(ctx, Jump::Continue)?,
+            Action::Call(val) => val.execute(ctx)?,
+            Action::Include(val) => val.execute(ctx)?,
         }
         Ok(())
     }
+
+    fn loop_count(&self) -> u32 {
+        match self {
+            Action::Loop(val) => val.loop_count(),
+            Action::If(val) => val.loop_count(),
+            Action::WaitUntil(val) => val.timeout_branch.loop_count(),
+            _ => 0,
+        }
+    }
 }
@@ -0,0 +1,154 @@
+use enigo::{Axis, Button, Coordinate, Direction, Enigo, Key, Keyboard, Mouse};
+use std::{sync::Arc, time::Duration};
+
+use crate::clock::{Clock, RealClock};
+use crate::errors::AppError;
+
+/// Everything the action engine needs from the system it sends input to.
+/// `Enigo` is the implementation that talks to the real OS.
+pub trait InputBackend {
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), AppError>;
+    fn button(&mut self, button: Button, direction: Direction) -> Result<(), AppError>;
+    fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> Result<(), AppError>;
+    fn location(&self) -> Result<(i32, i32), AppError>;
+    /// Scrolls `length` notches, positive values scroll down or right
+    fn scroll(&mut self, length: i32, axis: Axis) -> Result<(), AppError>;
+
+    /// Types `text` without pressing any modifiers, so every Unicode
+    /// character can be entered. Clicks one `Key::Unicode` per character
+    /// unless the backend has a native text entry.
+    fn text(&mut self, text: &str) -> Result<(), AppError> {
+        for c in text.chars() {
+            self.key(Key::Unicode(c), Direction::Click)?;
+        }
+        Ok(())
+    }
+}
+
+impl InputBackend for Enigo {
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), AppError> {
+        Keyboard::key(self, key, direction)?;
+        Ok(())
+    }
+    fn button(&mut self, button: Button, direction: Direction) -> Result<(), AppError> {
+        Mouse::button(self, button, direction)?;
+        Ok(())
+    }
+    fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> Result<(), AppError> {
+        Mouse::move_mouse(self, x, y, coordinate)?;
+        Ok(())
+    }
+    fn location(&self) -> Result<(i32, i32), AppError> {
+        Ok(Mouse::location(self)?)
+    }
+    fn scroll(&mut self, length: i32, axis: Axis) -> Result<(), AppError> {
+        Mouse::scroll(self, length, axis)?;
+        Ok(())
+    }
+    fn text(&mut self, text: &str) -> Result<(), AppError> {
+        Keyboard::text(self, text)?;
+        Ok(())
+    }
+}
+
+/// A single input event as seen by an [`InputBackend`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    Key(Key, Direction),
+    Button(Button, Direction),
+    MoveMouse(i32, i32, Coordinate),
+    /// One character typed with the backend's text entry
+    Text(char),
+    Scroll(i32, Axis),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecordedEvent {
+    /// Time of the backend's clock when the event was emitted
+    pub at: Duration,
+    pub event: InputEvent,
+}
+
+/// In-memory backend that never touches the OS. It keeps track of the cursor
+/// position and records every event it receives.
+#[derive(Clone)]
+pub struct RecordingBackend {
+    clock: Arc<dyn Clock>,
+    location: (i32, i32),
+    events: Vec<RecordedEvent>,
+}
+
+impl Default for RecordingBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RecordingBackend {
+    pub fn new() -> Self {
+        Self {
+            clock: Arc::new(RealClock::new()),
+            location: (0, 0),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn with_location(mut self, x: i32, y: i32) -> Self {
+        self.location = (x, y);
+        self
+    }
+
+    /// Timestamps events with `clock` instead of the wall clock
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    pub fn events(&self) -> &[RecordedEvent] {
+        &self.events
+    }
+
+    /// The recorded events without their timestamps
+    pub fn inputs(&self) -> Vec<InputEvent> {
+        self.events.iter().map(|e| e.event).collect()
+    }
+
+    fn record(&mut self, event: InputEvent) {
+        self.events.push(RecordedEvent {
+            at: self.clock.now(),
+            event,
+        });
+    }
+}
+
+impl InputBackend for RecordingBackend {
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), AppError> {
+        self.record(InputEvent::Key(key, direction));
+        Ok(())
+    }
+    fn button(&mut self, button: Button, direction: Direction) -> Result<(), AppError> {
+        self.record(InputEvent::Button(button, direction));
+        Ok(())
+    }
+    fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> Result<(), AppError> {
+        self.location = match coordinate {
+            Coordinate::Abs => (x, y),
+            Coordinate::Rel => (self.location.0 + x, self.location.1 + y),
+        };
+        self.record(InputEvent::MoveMouse(x, y, coordinate));
+        Ok(())
+    }
+    fn location(&self) -> Result<(i32, i32), AppError> {
+        Ok(self.location)
+    }
+    fn scroll(&mut self, length: i32, axis: Axis) -> Result<(), AppError> {
+        self.record(InputEvent::Scroll(length, axis));
+        Ok(())
+    }
+    fn text(&mut self, text: &str) -> Result<(), AppError> {
+        for c in text.chars() {
+            self.record(InputEvent::Text(c));
+        }
+        Ok(())
+    }
+}
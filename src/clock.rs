@@ -0,0 +1,149 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::signal::ControlSignal;
+
+/// Source of time for the action engine. All waiting during execution goes
+/// through a clock, so a macro can run in real time or be simulated.
+pub trait Clock: Send + Sync {
+    /// Time passed since the clock was created
+    fn now(&self) -> Duration;
+    /// Current date and time, for things scheduled at a time of day
+    fn wall_time(&self) -> SystemTime;
+    fn sleep(&self, duration: Duration);
+
+    /// Like `sleep`, but returns as soon as the macro is stopped or paused.
+    /// Returns whether it was interrupted.
+    fn sleep_interruptible(&self, duration: Duration, control: &ControlSignal) -> bool {
+        if !interrupted(control) {
+            self.sleep(duration);
+        }
+        interrupted(control)
+    }
+}
+
+/// Wall clock, sleeps the calling thread.
+#[derive(Debug, Clone, Copy)]
+pub struct RealClock {
+    start: Instant,
+}
+
+impl Default for RealClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RealClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for RealClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+
+    fn sleep_interruptible(&self, duration: Duration, control: &ControlSignal) -> bool {
+        control.wait_timeout(duration)
+    }
+}
+
+/// Simulated clock. Sleeping advances the simulated time, by default without
+/// waiting at all. Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct VirtualClock {
+    elapsed_ns: Arc<AtomicU64>,
+    speed: f64,
+    start_time: SystemTime,
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VirtualClock {
+    /// Clock where every sleep returns immediately
+    pub fn new() -> Self {
+        Self::with_speed(f64::INFINITY)
+    }
+
+    /// Clock that runs `speed` times faster than real time,
+    /// e.g. `2.0` sleeps for half of every requested duration.
+    pub fn with_speed(speed: f64) -> Self {
+        Self {
+            elapsed_ns: Arc::new(AtomicU64::new(0)),
+            speed,
+            start_time: SystemTime::UNIX_EPOCH,
+        }
+    }
+
+    /// Sets the wall time the clock starts at, the Unix epoch by default
+    pub fn starting_at(mut self, start_time: SystemTime) -> Self {
+        self.start_time = start_time;
+        self
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed_ns
+            .fetch_add(duration.as_nanos() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.elapsed_ns.load(Ordering::SeqCst))
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        self.start_time + self.now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        if self.speed.is_finite() && self.speed > 0.0 {
+            thread::sleep(duration.div_f64(self.speed));
+        }
+        self.advance(duration);
+    }
+
+    fn sleep_interruptible(&self, duration: Duration, control: &ControlSignal) -> bool {
+        if interrupted(control) {
+            return true;
+        }
+        if !self.speed.is_finite() || self.speed <= 0.0 {
+            self.advance(duration);
+            return false;
+        }
+        let start = Instant::now();
+        let interrupted = control.wait_timeout(duration.div_f64(self.speed));
+        if interrupted {
+            self.advance(start.elapsed().mul_f64(self.speed).min(duration));
+        } else {
+            self.advance(duration);
+        }
+        interrupted
+    }
+}
+
+fn interrupted(control: &ControlSignal) -> bool {
+    control.is_stopped() || control.is_paused()
+}
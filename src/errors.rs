@@ -7,4 +7,61 @@ pub enum AppError {
 
     #[error("Input error: {0}")]
     Input(#[from] enigo::InputError),
-}
\ No newline at end of file
+
+    #[error("Could not read macro: {0}")]
+    Deserialize(#[from] serde_json::Error),
+
+    #[error("Hotkey error: {0}")]
+    Hotkey(#[from] global_hotkey::Error),
+
+    #[error("Could not connect to the input backend: {0}")]
+    BackendInit(#[from] enigo::NewConError),
+
+    #[error("Could not read the screen: {0}")]
+    Screen(String),
+
+    #[error("Gave up waiting after {0} ms")]
+    Timeout(u64),
+
+    #[error("Expression error: {0}")]
+    Expression(String),
+
+    #[error("There is no enclosing loop named `{0}`")]
+    NoSuchLoop(String),
+
+    #[error("Procedure error: {0}")]
+    Procedure(String),
+
+    /// Reading or running an included file failed
+    #[error("In `{path}`: {source}")]
+    Include {
+        /// As written in the include action
+        path: String,
+        source: Box<AppError>,
+    },
+
+    #[error("`{0}` includes itself")]
+    IncludeCycle(String),
+
+    /// An action failed while the macro was running
+    #[error("Action {path} failed: {source}")]
+    Action {
+        /// Label of the action like the editor shows it, e.g. `1.3`
+        path: String,
+        source: Box<AppError>,
+    },
+}
+
+impl AppError {
+    /// Attributes the error to the action at `path`, unless it already
+    /// belongs to a more deeply nested action
+    pub fn in_action(self, path: String) -> Self {
+        match self {
+            AppError::Action { .. } => self,
+            source => AppError::Action {
+                path,
+                source: Box::new(source),
+            },
+        }
+    }
+}
@@ -0,0 +1,379 @@
+use serde::{Deserialize, Serialize};
+
+use crate::errors::AppError;
+use crate::ranged::{Number, Ranged};
+
+/// Value of a variable or an expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Int(i64),
+    Str(String),
+    Bool(bool),
+}
+
+impl Value {
+    pub fn as_int(&self) -> Result<i64, AppError> {
+        match self {
+            Value::Int(n) => Ok(*n),
+            _ => Err(AppError::Expression(format!(
+                "{} is not a number",
+                self.quoted()
+            ))),
+        }
+    }
+
+    pub fn as_bool(&self) -> Result<bool, AppError> {
+        match self {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(AppError::Expression(format!(
+                "{} is not true or false",
+                self.quoted()
+            ))),
+        }
+    }
+
+    /// Strings in quotes, to tell them apart in error messages
+    fn quoted(&self) -> String {
+        match self {
+            Value::Str(s) => format!("\"{}\"", s),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{}", n),
+            Value::Str(s) => write!(f, "{}", s),
+            Value::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Int(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Str(value.to_string())
+    }
+}
+
+/// A number of an action that is either given directly or computed
+/// from variables every time the action runs.
+///
+/// Serialized as the `Ranged` or as the source of the expression,
+/// so files written before expressions existed still load.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Param<T: Number> {
+    Ranged(Ranged<T>),
+    /// Source of an expression like `100 + i * 40`
+    Expr(String),
+}
+
+impl<T: Number> Default for Param<T> {
+    fn default() -> Self {
+        Param::Ranged(Ranged::default())
+    }
+}
+
+impl<T: Number> From<T> for Param<T> {
+    fn from(value: T) -> Self {
+        Param::Ranged(value.into())
+    }
+}
+
+impl<T: Number> From<Ranged<T>> for Param<T> {
+    fn from(value: Ranged<T>) -> Self {
+        Param::Ranged(value)
+    }
+}
+
+/// Parsed arithmetic and logic on integers, strings, booleans and variables.
+///
+/// `+ - * / %` work on integers with the usual precedence, `+` joins
+/// strings if either side is one. String literals are written in double quotes.
+/// `== != < <= > >=` compare two integers or two strings and bind weaker,
+/// `!`, `&&` and `||` combine `true` and `false` like in Rust.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Int(i64),
+    Str(String),
+    Bool(bool),
+    Var(String),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+impl BinOp {
+    fn from_symbol(symbol: &str) -> Option<Self> {
+        Some(match symbol {
+            "+" => BinOp::Add,
+            "-" => BinOp::Sub,
+            "*" => BinOp::Mul,
+            "/" => BinOp::Div,
+            "%" => BinOp::Rem,
+            "==" => BinOp::Eq,
+            "!=" => BinOp::Ne,
+            "<" => BinOp::Lt,
+            "<=" => BinOp::Le,
+            ">" => BinOp::Gt,
+            ">=" => BinOp::Ge,
+            "&&" => BinOp::And,
+            "||" => BinOp::Or,
+            _ => return None,
+        })
+    }
+
+    /// Operators with a higher precedence bind tighter
+    fn precedence(self) -> u8 {
+        match self {
+            BinOp::Or => 1,
+            BinOp::And => 2,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => 3,
+            BinOp::Add | BinOp::Sub => 4,
+            BinOp::Mul | BinOp::Div | BinOp::Rem => 5,
+        }
+    }
+
+    /// `And` and `Or` are evaluated in `Expr::eval`, they skip the right side if possible
+    fn apply(self, lhs: Value, rhs: Value) -> Result<Value, AppError> {
+        match self {
+            BinOp::Eq => return Ok(Value::Bool(lhs == rhs)),
+            BinOp::Ne => return Ok(Value::Bool(lhs != rhs)),
+            BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+                let ordering = match (&lhs, &rhs) {
+                    (Value::Int(a), Value::Int(b)) => a.cmp(b),
+                    (Value::Str(a), Value::Str(b)) => a.cmp(b),
+                    _ => {
+                        return Err(AppError::Expression(format!(
+                            "can't compare {} with {}",
+                            lhs.quoted(),
+                            rhs.quoted()
+                        )))
+                    }
+                };
+                return Ok(Value::Bool(match self {
+                    BinOp::Lt => ordering.is_lt(),
+                    BinOp::Le => ordering.is_le(),
+                    BinOp::Gt => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                }));
+            }
+            _ => {}
+        }
+        if let (BinOp::Add, Value::Str(_), _) | (BinOp::Add, _, Value::Str(_)) = (self, &lhs, &rhs)
+        {
+            return Ok(Value::Str(format!("{}{}", lhs, rhs)));
+        }
+        let (lhs, rhs) = (lhs.as_int()?, rhs.as_int()?);
+        let result = match self {
+            BinOp::Add => lhs.checked_add(rhs),
+            BinOp::Sub => lhs.checked_sub(rhs),
+            BinOp::Mul => lhs.checked_mul(rhs),
+            BinOp::Div | BinOp::Rem if rhs == 0 => {
+                return Err(AppError::Expression("division by zero".to_string()))
+            }
+            BinOp::Div => lhs.checked_div(rhs),
+            BinOp::Rem => lhs.checked_rem(rhs),
+            _ => unreachable!("{:?} is not arithmetic", self),
+        };
+        result
+            .map(Value::Int)
+            .ok_or_else(|| AppError::Expression("number too large".to_string()))
+    }
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Expr, AppError> {
+        let tokens = tokenize(source).map_err(AppError::Expression)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_binary(0).map_err(AppError::Expression)?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(expr),
+            Some(token) => Err(AppError::Expression(format!("unexpected {}", token))),
+        }
+    }
+
+    /// `lookup` returns the value of a variable, `None` if it does not exist
+    pub fn eval(&self, lookup: &dyn Fn(&str) -> Option<Value>) -> Result<Value, AppError> {
+        match self {
+            Expr::Int(n) => Ok(Value::Int(*n)),
+            Expr::Str(s) => Ok(Value::Str(s.clone())),
+            Expr::Bool(b) => Ok(Value::Bool(*b)),
+            Expr::Var(name) => lookup(name)
+                .ok_or_else(|| AppError::Expression(format!("unknown variable `{}`", name))),
+            Expr::Neg(expr) => expr
+                .eval(lookup)?
+                .as_int()?
+                .checked_neg()
+                .map(Value::Int)
+                .ok_or_else(|| AppError::Expression("number too large".to_string())),
+            Expr::Not(expr) => Ok(Value::Bool(!expr.eval(lookup)?.as_bool()?)),
+            Expr::Binary(BinOp::And, lhs, rhs) => Ok(Value::Bool(
+                lhs.eval(lookup)?.as_bool()? && rhs.eval(lookup)?.as_bool()?,
+            )),
+            Expr::Binary(BinOp::Or, lhs, rhs) => Ok(Value::Bool(
+                lhs.eval(lookup)?.as_bool()? || rhs.eval(lookup)?.as_bool()?,
+            )),
+            Expr::Binary(op, lhs, rhs) => op.apply(lhs.eval(lookup)?, rhs.eval(lookup)?),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Int(i64),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+    Open,
+    Close,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Int(n) => write!(f, "{}", n),
+            Token::Str(s) => write!(f, "\"{}\"", s),
+            Token::Ident(name) => write!(f, "`{}`", name),
+            Token::Op(op) => write!(f, "`{}`", op),
+            Token::Open => write!(f, "`(`"),
+            Token::Close => write!(f, "`)`"),
+        }
+    }
+}
+
+/// Longer operators first, so they are not split up
+const OPERATORS: [&str; 14] = [
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "+", "-", "*", "/", "%",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let len = if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let n = rest[..len]
+                .parse()
+                .map_err(|_| format!("{} is too large", &rest[..len]))?;
+            tokens.push(Token::Int(n));
+            len
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..len].to_string()));
+            len
+        } else if c == '"' {
+            let end = rest[1..].find('"').ok_or("missing closing `\"`")?;
+            tokens.push(Token::Str(rest[1..=end].to_string()));
+            end + 2
+        } else if c == '(' {
+            tokens.push(Token::Open);
+            1
+        } else if c == ')' {
+            tokens.push(Token::Close);
+            1
+        } else if let Some(op) = OPERATORS.into_iter().find(|op| rest.starts_with(op)) {
+            tokens.push(Token::Op(op));
+            op.len()
+        } else {
+            return Err(format!("unexpected `{}`", c));
+        };
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Parses operators binding at least as tight as `min_precedence`
+    fn parse_binary(&mut self, min_precedence: u8) -> Result<Expr, String> {
+        let mut lhs = self.parse_unary()?;
+        while let Some(Token::Op(symbol)) = self.tokens.get(self.pos) {
+            let Some(op) = BinOp::from_symbol(symbol) else {
+                break;
+            };
+            if op.precedence() < min_precedence {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.parse_binary(op.precedence() + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op("-")) => {
+                self.pos += 1;
+                Ok(Expr::Neg(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::Op("!")) => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.parse_unary()?)))
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Int(n)) => Ok(Expr::Int(n)),
+            Some(Token::Str(s)) => Ok(Expr::Str(s)),
+            Some(Token::Ident(name)) => Ok(match name.as_str() {
+                "true" => Expr::Bool(true),
+                "false" => Expr::Bool(false),
+                _ => Expr::Var(name),
+            }),
+            Some(Token::Open) => {
+                let expr = self.parse_binary(0)?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    Some(token) => Err(format!("expected `)`, found {}", token)),
+                    None => Err("missing `)`".to_string()),
+                }
+            }
+            Some(token) => Err(format!("unexpected {}", token)),
+            None => Err("unexpected end".to_string()),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod actions;
+pub mod backend;
+pub mod clock;
+pub mod errors;
+pub mod expr;
+pub mod ranged;
+pub mod screen;
+pub mod signal;
+pub mod simulation;
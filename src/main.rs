@@ -1,17 +1,22 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
-#[cfg(target_os = "windows")]
-mod actions;
-mod errors;
-
-use crate::actions::*;
+use auto_clicker::actions::*;
+use auto_clicker::clock::RealClock;
+use auto_clicker::errors::AppError;
+use auto_clicker::expr::{Expr, Param};
+use auto_clicker::ranged::{Number, Ranged, Variation};
+use auto_clicker::screen::{Color, SystemScreen, MAX_RADIUS};
+use auto_clicker::signal::{ControlSignal, Progress, Step};
+use auto_clicker::simulation::{simulate_on, Simulation, SimulationOptions};
 use eframe::egui::{self, Color32, Ui};
-use enigo::{Enigo, Settings};
+use enigo::{Enigo, Mouse, Settings};
 use global_hotkey::{
     hotkey::{Code, HotKey, Modifiers},
     GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState,
 };
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::Arc;
 use std::time::Duration;
 use std::thread;
@@ -41,35 +46,175 @@ struct MyApp {
     root_action: LoopAction,
     start_thread: Arc<AtomicBool>,
     stop_thread: Arc<AtomicBool>,
+    pause_thread: Arc<AtomicBool>,
     thread_running: Arc<AtomicBool>,
     thread_handle: JoinHandle<()>,
-    thread_stop_signal: Arc<AtomicBool>,
-    hotkey_manager: GlobalHotKeyManager,
+    thread_control: ControlSignal,
+    thread_progress: Progress,
+    hotkey_manager: Option<GlobalHotKeyManager>,
     save_name: String,
+    /// Where the macro was last loaded from or saved to
+    file: Option<PathBuf>,
+    simulation: Option<Simulation>,
+    messages: Vec<StatusMessage>,
+    message_sender: Sender<WorkerMessage>,
+    message_receiver: Receiver<WorkerMessage>,
+    /// Whether a simulation is running in the background
+    simulating: bool,
+}
+
+/// Entry of the message history in the side panel
+struct StatusMessage {
+    text: String,
+    is_error: bool,
+}
+
+/// What the macro and simulation threads report back to the GUI
+enum WorkerMessage {
+    Status(StatusMessage),
+    Simulated(Result<Simulation, AppError>),
+}
+
+impl StatusMessage {
+    fn info(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            is_error: false,
+        }
+    }
+
+    fn error(context: &str, err: &AppError) -> Self {
+        Self {
+            text: format!("{}: {}", context, err),
+            is_error: true,
+        }
+    }
 }
 
 impl MyApp {
     fn start_thread(&mut self, ctx: &egui::Context) {
         self.stop_thread();
-        let mut enigo = Enigo::new(&Settings::default()).unwrap();
+        let mut enigo = match Enigo::new(&Settings::default()) {
+            Ok(enigo) => enigo,
+            Err(err) => {
+                self.report(StatusMessage::error("Could not start", &err.into()));
+                return;
+            }
+        };
         let action_copy = self.root_action.clone();
-        let stop_signal = self.thread_stop_signal.clone();
+        let file = self.file.clone();
+        let messages = self.message_sender.clone();
+        let control = self.thread_control.clone();
+        let progress = self.thread_progress.clone();
+        // set before spawning, so a pause right after the start is not lost
+        self.thread_running.store(true, Ordering::SeqCst);
         let running = self.thread_running.clone();
         let ctx_clone = ctx.clone();
         self.thread_handle = thread::spawn(move || {
-            running.store(true, Ordering::SeqCst);
-            if let Err(err) = action_copy.execute(&mut enigo, Some(stop_signal)) {
-                println!("Execution Thread encountered an error: {}", err);
+            let clock = RealClock::new();
+            let mut ctx = ExecutionContext::new(&mut enigo, &clock)
+                .with_screen(&SystemScreen)
+                .with_control(control)
+                .with_progress(progress);
+            if let Some(file) = file {
+                ctx = ctx.with_file(file);
             }
+            let message = match action_copy.execute(&mut ctx) {
+                Ok(()) => StatusMessage::info("Macro finished"),
+                Err(err) => StatusMessage::error("Macro failed", &err),
+            };
+            // the receiver only goes away together with the GUI
+            let _ = messages.send(WorkerMessage::Status(message));
             running.store(false, Ordering::SeqCst);
             ctx_clone.request_repaint();
         });
     }
 
+    fn simulate(&mut self, ctx: &egui::Context) {
+        let start_location = Enigo::new(&Settings::default())
+            .ok()
+            .and_then(|enigo| enigo.location().ok())
+            .unwrap_or((0, 0));
+        let options = SimulationOptions {
+            start_location,
+            file: self.file.clone(),
+            ..Default::default()
+        };
+        let root = self.root_action.clone();
+        let messages = self.message_sender.clone();
+        let ctx = ctx.clone();
+        self.simulating = true;
+        // long simulations must not freeze the GUI
+        thread::spawn(move || {
+            let result = simulate_on(&root, options, &SystemScreen);
+            let _ = messages.send(WorkerMessage::Simulated(result));
+            ctx.request_repaint();
+        });
+    }
+
+    fn report(&mut self, message: StatusMessage) {
+        self.messages.push(message);
+    }
+
+    fn load(&mut self) {
+        if let Some(path) = rfd::FileDialog::new().pick_file() {
+            match self.root_action.load_from_disk(&path) {
+                Ok(_) => {
+                    self.save_name = path
+                        .file_stem()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    self.report(StatusMessage::info(format!("Loaded '{}'", path.display())));
+                    self.file = Some(path);
+                }
+                Err(err) => self.report(StatusMessage::error(
+                    &format!("Could not load '{}'", path.display()),
+                    &err,
+                )),
+            }
+        }
+    }
+
+    fn save(&mut self) {
+        let mut dialog = rfd::FileDialog::new().set_file_name(format!(
+            "{}.aclick",
+            if self.save_name.is_empty() {
+                "save"
+            } else {
+                self.save_name.as_str()
+            }
+        ));
+        if let Ok(curr_path) = std::env::current_dir() {
+            dialog = dialog.set_directory(&curr_path);
+        }
+        if let Some(path) = dialog.save_file() {
+            match self.root_action.save_to_disk(&path) {
+                Ok(_) => {
+                    self.report(StatusMessage::info(format!("Saved '{}'", path.display())));
+                    self.file = Some(path);
+                }
+                Err(err) => self.report(StatusMessage::error(
+                    &format!("Could not save '{}'", path.display()),
+                    &err,
+                )),
+            }
+        }
+    }
+
     fn stop_thread(&mut self) {
-        self.thread_stop_signal.store(true, Ordering::Relaxed);
-        self.thread_stop_signal = Arc::new(AtomicBool::new(false));
+        self.thread_control.stop();
+        self.thread_control = ControlSignal::new();
+        self.thread_progress = Progress::new();
+        // the stopped thread may still be releasing keys, it must not
+        // reset the flag of a run started after it
         self.thread_running.store(false, Ordering::SeqCst);
+        self.thread_running = Arc::new(AtomicBool::new(false));
+    }
+
+    fn toggle_pause_thread(&mut self) {
+        if self.thread_running.load(Ordering::SeqCst) {
+            self.thread_control.toggle_pause();
+        }
     }
 }
 
@@ -79,38 +224,62 @@ impl MyApp {
         mods.insert(Modifiers::CONTROL);
         let hotkey_start = HotKey::new(Some(mods), Code::F6);
         let hotkey_stop = HotKey::new(Some(mods), Code::F7);
+        let hotkey_pause = HotKey::new(Some(mods), Code::F8);
 
-        let myapp = Self {
+        let (message_sender, message_receiver) = mpsc::channel();
+        let mut myapp = Self {
             root_action: LoopAction {
+                name: String::new(),
                 infinite: true,
-                iterations: 1,
+                iterations: 1.into(),
                 actions: Vec::new(),
+                breakpoints: Vec::new(),
+                time_limit: None,
+                procedures: Vec::new(),
             },
             stop_thread: Arc::new(AtomicBool::new(false)),
             start_thread: Arc::new(AtomicBool::new(false)),
+            pause_thread: Arc::new(AtomicBool::new(false)),
             thread_running: Arc::new(AtomicBool::new(false)),
-            thread_stop_signal: Arc::new(AtomicBool::new(false)),
+            thread_control: ControlSignal::new(),
+            thread_progress: Progress::new(),
             thread_handle: thread::spawn(|| {}),
-            hotkey_manager: GlobalHotKeyManager::new().unwrap(),
+            hotkey_manager: None,
             save_name: String::new(),
+            file: None,
+            simulation: None,
+            messages: Vec::new(),
+            message_sender,
+            message_receiver,
+            simulating: false,
         };
 
-        let res = myapp.hotkey_manager.register(hotkey_start);
-        match res {
-            Ok(_) => println!("Successfully registered hotkey_start"),
-            Err(val) => println!("Could not register hotkey_start: {:?}", val),
-        }
-        let res = myapp.hotkey_manager.register(hotkey_stop);
-        match res {
-            Ok(_) => println!("Successfully registered hotkey_stop"),
-            Err(val) => println!("Could not register hotkey_stop: {:?}", val),
+        match GlobalHotKeyManager::new() {
+            Ok(manager) => {
+                for (name, hotkey) in [
+                    ("ctrl+shift+F6", hotkey_start),
+                    ("ctrl+shift+F7", hotkey_stop),
+                    ("ctrl+shift+F8", hotkey_pause),
+                ] {
+                    if let Err(err) = manager.register(hotkey) {
+                        myapp.report(StatusMessage::error(
+                            &format!("Could not register hotkey '{}'", name),
+                            &err.into(),
+                        ));
+                    }
+                }
+                myapp.hotkey_manager = Some(manager);
+            }
+            Err(err) => myapp.report(StatusMessage::error("Hotkeys are unavailable", &err.into())),
         }
 
         let ctx_clone = ctx.clone();
         let start_thread = myapp.start_thread.clone();
         let stop_thread = myapp.stop_thread.clone();
+        let pause_thread = myapp.pause_thread.clone();
         let hotkey_start_id = hotkey_start.id();
         let hotkey_stop_id = hotkey_stop.id();
+        let hotkey_pause_id = hotkey_pause.id();
         thread::spawn(move || loop {
             if let Ok(event) = GlobalHotKeyEvent::receiver().recv() {
                 if event.id == hotkey_start_id {
@@ -121,6 +290,10 @@ impl MyApp {
                     if event.state == HotKeyState::Pressed {
                         stop_thread.store(true, Ordering::SeqCst);
                     }
+                } else if event.id == hotkey_pause_id {
+                    if event.state == HotKeyState::Pressed {
+                        pause_thread.store(true, Ordering::SeqCst);
+                    }
                 } else {
                     println!("Unhandled Event: {:?}", event);
                 }
@@ -136,6 +309,18 @@ impl MyApp {
 
 impl eframe::App for MyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        while let Ok(message) = self.message_receiver.try_recv() {
+            match message {
+                WorkerMessage::Status(message) => self.report(message),
+                WorkerMessage::Simulated(result) => {
+                    self.simulating = false;
+                    match result {
+                        Ok(simulation) => self.simulation = Some(simulation),
+                        Err(err) => self.report(StatusMessage::error("Simulation failed", &err)),
+                    }
+                }
+            }
+        }
         egui::SidePanel::new(egui::panel::Side::Left, egui::Id::new("Right side"))
             .exact_width(150.0)
             .show(ctx, |ui| {
@@ -158,49 +343,59 @@ impl eframe::App for MyApp {
                     });
                     ui.group(|ui| {
                         ui.horizontal(|ui| {
-                            if ui.button("Load").clicked() {
-                                if let Some(path) = rfd::FileDialog::new().pick_file() {
-                                    let res = self.root_action.load_from_disk(&path);
-                                    match res {
-                                        Ok(_) => {
-                                            self.save_name = String::from(
-                                                path.file_stem().unwrap().to_str().unwrap(),
-                                            )
-                                        }
-                                        Err(val) => println!(
-                                            "Could not read from disk: '{}'",
-                                            val.to_string()
-                                        ),
-                                    }
+                            let label = if self.thread_control.is_paused() {
+                                "Resume"
+                            } else {
+                                "Pause"
+                            };
+                            if ui.button(label).clicked() {
+                                self.pause_thread.store(true, Ordering::SeqCst);
+                            }
+                            ui.label("or 'ctrl+shift+F8'");
+                        });
+                        if self.thread_running.load(Ordering::SeqCst)
+                            && self.thread_control.is_paused()
+                        {
+                            ui.horizontal(|ui| {
+                                if ui.button("Step Into").clicked() {
+                                    self.thread_control.step(Step::Into);
+                                }
+                                if ui.button("Step Over").clicked() {
+                                    self.thread_control.step(Step::Over);
                                 }
+                            });
+                            if ui.button("Continue").clicked() {
+                                self.thread_control.resume();
+                            }
+                        }
+                    });
+                    ui.group(|ui| {
+                        ui.horizontal(|ui| {
+                            if ui.button("Load").clicked() {
+                                self.load();
                             }
                             if ui.button("Save").clicked() {
-                                let curr_path = std::env::current_dir().unwrap();
-                                let path = rfd::FileDialog::new()
-                                    .set_file_name(format!(
-                                        "{}.aclick",
-                                        if self.save_name.is_empty() {
-                                            "save"
-                                        } else {
-                                            self.save_name.as_str()
-                                        }
-                                    ))
-                                    .set_directory(&curr_path)
-                                    .save_file();
-                                if path.is_some() {
-                                    if let Err(err) = self.root_action.save_to_disk(&path.unwrap())
-                                    {
-                                        println!("Could not save to disk: '{}'", err.to_string())
-                                    }
-                                }
+                                self.save();
                             }
                         });
                     });
+                    ui.group(|ui| {
+                        if ui
+                            .add_enabled(!self.simulating, egui::Button::new("Simulate"))
+                            .clicked()
+                        {
+                            self.simulate(ctx);
+                        }
+                    });
                     ui.group(|ui| {
                         ui.horizontal(|ui| {
-                            ui.label("Running: ");
+                            let running = self.thread_running.load(Ordering::SeqCst);
+                            let paused = running && self.thread_control.is_paused();
+                            ui.label(if paused { "Paused: " } else { "Running: " });
                             ui.add_space(10.0);
-                            let circle_color = if self.thread_running.load(Ordering::SeqCst) {
+                            let circle_color = if paused {
+                                Color32::from_rgb(255, 200, 10)
+                            } else if running {
                                 Color32::from_rgb(10, 255, 10)
                             } else {
                                 Color32::from_rgb(255, 10, 10)
@@ -213,12 +408,47 @@ impl eframe::App for MyApp {
                             ui.add_space(10.0);
                         });
                     });
+                    ui.group(|ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Messages");
+                            if ui.button("Clear").clicked() {
+                                self.messages.clear();
+                            }
+                        });
+                        egui::ScrollArea::vertical()
+                            .max_height(250.0)
+                            .show(ui, |ui| {
+                                for message in self.messages.iter().rev() {
+                                    let mut text = egui::RichText::new(&message.text);
+                                    if message.is_error {
+                                        text = text.color(ui.visuals().error_fg_color);
+                                    }
+                                    ui.label(text);
+                                    ui.separator();
+                                }
+                            });
+                    });
                 });
             });
+        show_simulation(ctx, &mut self.simulation);
+        let progress = if self.thread_running.load(Ordering::SeqCst) {
+            // keep the highlighted action and loop counters up to date
+            ctx.request_repaint_after(Duration::from_millis(50));
+            self.thread_progress.get()
+        } else {
+            Vec::new()
+        };
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::ScrollArea::vertical().show(ui, |ui| {
                 let mut loop_index: u32 = 0;
-                add_loop_action(ui, &mut self.root_action, &mut loop_index, 0);
+                add_loop_action(ui, &mut self.root_action, &mut loop_index, 0, &progress);
+                ui.add_space(10.0);
+                add_procedures(
+                    ui,
+                    &mut self.root_action.procedures,
+                    &mut loop_index,
+                    &progress,
+                );
             });
         });
 
@@ -230,457 +460,1476 @@ impl eframe::App for MyApp {
             self.stop_thread();
             self.stop_thread.store(false, Ordering::SeqCst);
         }
+        if self.pause_thread.load(Ordering::SeqCst) {
+            self.toggle_pause_thread();
+            self.pause_thread.store(false, Ordering::SeqCst);
+        }
     }
 }
 
-fn add_loop_action(ui: &mut Ui, loopaction: &mut LoopAction, loop_index: &mut u32, depth: u16) {
-    let mut index_to_rm: Option<usize> = None;
+fn show_simulation(ctx: &egui::Context, simulation: &mut Option<Simulation>) {
+    let mut open = simulation.is_some();
+    if let Some(sim) = simulation {
+        egui::Window::new("Simulation")
+            .open(&mut open)
+            .default_size([500.0, 400.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} events in {:.3} s{}",
+                    sim.timeline.len(),
+                    sim.duration.as_secs_f64(),
+                    if sim.truncated {
+                        " (cut off, the macro runs longer)"
+                    } else {
+                        ""
+                    }
+                ));
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::Grid::new("simulation timeline")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.strong("Time (ms)");
+                            ui.strong("Action");
+                            ui.strong("Event");
+                            ui.strong("Cursor");
+                            ui.end_row();
+                            for entry in &sim.timeline {
+                                ui.label(format!("{:.1}", entry.at.as_secs_f64() * 1000.0));
+                                ui.label(&entry.path);
+                                ui.label(format!("{:?}", entry.event));
+                                ui.label(format!("{}, {}", entry.location.0, entry.location.1));
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+    }
+    if !open {
+        *simulation = None;
+    }
+}
+
+/// `progress` holds the position of the running macro, its current action gets highlighted
+fn add_loop_action(
+    ui: &mut Ui,
+    loopaction: &mut LoopAction,
+    loop_index: &mut u32,
+    depth: u16,
+    progress: &[LoopFrame],
+) {
     *loop_index += 1;
     let current_loop_index = *loop_index;
+    let running_frame = progress
+        .iter()
+        .find(|frame| frame.loop_index == current_loop_index);
 
     ui.horizontal(|ui| {
-        ui.checkbox(&mut loopaction.infinite, "Infinite Loop");
+        ui.vertical(|ui| {
+            ui.checkbox(&mut loopaction.infinite, "Infinite Loop");
+            ui.horizontal(|ui| {
+                ui.label("Name: ");
+                ui.add(egui::TextEdit::singleline(&mut loopaction.name).desired_width(80.0))
+                    .on_hover_text("Lets a break or continue in a nested loop refer to this one");
+            });
+            add_time_limit(ui, current_loop_index, &mut loopaction.time_limit);
+            if let Some(frame) = running_frame {
+                match &loopaction.iterations {
+                    Param::Ranged(iterations)
+                        if !loopaction.infinite && iterations.variation == Variation::Fixed =>
+                    {
+                        ui.label(format!(
+                            "iteration {}/{}",
+                            frame.iteration + 1,
+                            iterations.value
+                        ));
+                    }
+                    _ => {
+                        ui.label(format!("iteration {}", frame.iteration + 1));
+                    }
+                }
+            }
+        });
         if !loopaction.infinite {
             ui.label("iterations: ");
-            ui.add(egui::DragValue::new(&mut loopaction.iterations));
+            add_param(ui, &mut loopaction.iterations);
         }
         ui.separator();
         ui.add_space(10.0);
-        ui.vertical(|ui| {
-            for (pos, action) in loopaction.actions.iter_mut().enumerate() {
-                ui.group(|ui| {
-                    ui.horizontal(|ui| {
-                        ui.label(format!("{}.{}: ", current_loop_index, pos));
-                        match action {
-                            Action::Loop(val) => {
-                                add_loop_action(ui, val, loop_index, depth + 1);
+        add_action_list(
+            ui,
+            loopaction,
+            current_loop_index,
+            loop_index,
+            depth,
+            progress,
+        );
+    });
+    // only the procedures of the root loop are drawn, the ones of nested
+    // loops can only come from edited files
+    if depth > 0 {
+        for procedure in &loopaction.procedures {
+            *loop_index += procedure.body.loop_count();
+        }
+    }
+}
+
+/// The procedures of the root loop, their bodies are numbered after it
+fn add_procedures(
+    ui: &mut Ui,
+    procedures: &mut Vec<Procedure>,
+    loop_index: &mut u32,
+    progress: &[LoopFrame],
+) {
+    let mut index_to_rm: Option<usize> = None;
+    ui.heading("Procedures");
+    for (pos, procedure) in procedures.iter_mut().enumerate() {
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Name: ");
+                ui.add(egui::TextEdit::singleline(&mut procedure.name).desired_width(100.0));
+                ui.add_space(10.0);
+                ui.label("Parameters: ");
+                add_text_list(ui, &mut procedure.params, |ui, param| {
+                    ui.add(egui::TextEdit::singleline(param).desired_width(60.0));
+                });
+                let trash_icon = egui::include_image!("../assets/trash.svg");
+                if ui
+                    .add(egui::Button::image_and_text(trash_icon, ""))
+                    .clicked()
+                {
+                    index_to_rm = Some(pos);
+                }
+            });
+            add_loop_action(ui, &mut procedure.body, loop_index, 1, progress);
+        });
+    }
+    if let Some(index) = index_to_rm {
+        procedures.remove(index);
+    }
+    if ui.button("Add Procedure").clicked() {
+        procedures.push(Procedure {
+            name: format!("procedure{}", procedures.len() + 1),
+            params: Vec::new(),
+            body: LoopAction::once(Vec::new()),
+        });
+    }
+}
+
+/// One field per entry and buttons to add or remove the last one
+fn add_text_list(ui: &mut Ui, list: &mut Vec<String>, mut edit: impl FnMut(&mut Ui, &mut String)) {
+    for entry in list.iter_mut() {
+        edit(ui, entry);
+    }
+    if ui.small_button("+").clicked() {
+        list.push(String::new());
+    }
+    if !list.is_empty() && ui.small_button("-").clicked() {
+        list.pop();
+    }
+}
+
+/// The actions of the loop labeled `current_loop_index`, `loop_index` counts
+/// the loops drawn so far
+fn add_action_list(
+    ui: &mut Ui,
+    loopaction: &mut LoopAction,
+    current_loop_index: u32,
+    loop_index: &mut u32,
+    depth: u16,
+    progress: &[LoopFrame],
+) {
+    let mut index_to_rm: Option<usize> = None;
+    let mut breakpoint_to_toggle: Option<usize> = None;
+    let running_action = progress
+        .last()
+        .filter(|frame| frame.loop_index == current_loop_index)
+        .map(|frame| frame.action);
+
+    ui.vertical(|ui| {
+        for (pos, action) in loopaction.actions.iter_mut().enumerate() {
+            let mut row = egui::Frame::group(ui.style());
+            if running_action == Some(pos) {
+                row = row.fill(ui.visuals().selection.bg_fill.gamma_multiply(0.5));
+            }
+            row.show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    let breakpoint_color = if loopaction.breakpoints.contains(&pos) {
+                        Color32::from_rgb(255, 10, 10)
+                    } else {
+                        ui.visuals().weak_text_color()
+                    };
+                    if ui
+                        .add(
+                            egui::Label::new(egui::RichText::new("●").color(breakpoint_color))
+                                .sense(egui::Sense::click()),
+                        )
+                        .on_hover_text("Toggle breakpoint")
+                        .clicked()
+                    {
+                        breakpoint_to_toggle = Some(pos);
+                    }
+                    ui.label(format!("{}.{}: ", current_loop_index, pos));
+                    match action {
+                        Action::Loop(val) => {
+                            add_loop_action(ui, val, loop_index, depth + 1, progress);
+                        }
+                        Action::If(val) => {
+                            add_if_action(
+                                ui,
+                                val,
+                                (current_loop_index, pos),
+                                loop_index,
+                                depth + 1,
+                                progress,
+                            );
+                        }
+                        Action::WaitUntil(val) => {
+                            add_wait_until_action(
+                                ui,
+                                val,
+                                (current_loop_index, pos),
+                                loop_index,
+                                depth + 1,
+                                progress,
+                            );
+                        }
+                        Action::Move(val) => {
+                            ui.label("x: ");
+                            add_param(ui, &mut val.x);
+                            ui.add_space(10.0);
+                            ui.label("y: ");
+                            add_param(ui, &mut val.y);
+                            ui.add_space(5.0);
+                            ui.checkbox(&mut val.relative, "Relative");
+                            ui.add_space(5.0);
+                            ui.label("Move-Time (ms): ");
+                            add_param(ui, &mut val.move_time_ms);
+                            let timed = !matches!(
+                                &val.move_time_ms,
+                                Param::Ranged(move_time_ms) if move_time_ms.bounds().1 == 0
+                            );
+                            if timed {
+                                egui::ComboBox::new((current_loop_index, pos), "")
+                                    .selected_text(format!("{:?}", val.path.shape))
+                                    .show_ui(ui, |ui| {
+                                        for shape in [
+                                            PathShape::Linear,
+                                            PathShape::EaseInOut,
+                                            PathShape::Bezier,
+                                            PathShape::Overshoot,
+                                        ] {
+                                            ui.selectable_value(
+                                                &mut val.path.shape,
+                                                shape,
+                                                format!("{:?}", shape),
+                                            );
+                                        }
+                                    });
+                                ui.label("Jitter (px): ");
+                                ui.add(egui::DragValue::new(&mut val.path.jitter_px));
                             }
-                            Action::Move(val) => {
-                                ui.label("x: ");
-                                ui.add(egui::DragValue::new(&mut val.x));
+                            ui.add_space(7.0);
+                            ui.label("Delay after (ms): ");
+                            add_param(ui, &mut val.delay_after_ms);
+                        }
+                        Action::Press(val) => {
+                            add_keybutton_combo(
+                                ui,
+                                pos + (current_loop_index as usize) * 5000,
+                                "Key",
+                                &mut val.keybutton,
+                            );
+
+                            ui.add_space(10.0);
+                            ui.vertical(|ui| {
+                                if ui.checkbox(&mut val.down, "Key-Down").clicked()
+                                    && !val.down
+                                    && !val.up
+                                {
+                                    val.up = true;
+                                };
+                                if ui.checkbox(&mut val.up, "Key-Up").clicked()
+                                    && !val.down
+                                    && !val.up
+                                {
+                                    val.down = true;
+                                };
+                            });
+                            if val.down && val.up {
+                                ui.label("Hold-Time (ms): ");
+                                add_param(ui, &mut val.hold_time_ms);
                                 ui.add_space(10.0);
-                                ui.label("y: ");
-                                ui.add(egui::DragValue::new(&mut val.y));
-                                ui.add_space(5.0);
-                                ui.checkbox(&mut val.relative, "Relative");
-                                ui.add_space(5.0);
-                                ui.label("Move-Time (ms): ");
-                                ui.add(egui::DragValue::new(&mut val.move_time_ms));
-                                ui.add_space(7.0);
-                                ui.label("Delay after (ms): ");
-                                ui.add(egui::DragValue::new(&mut val.delay_after_ms));
+                            } else {
+                                ui.add_space(157.0);
+                            }
+                            ui.label("Delay after (ms): ");
+                            add_param(ui, &mut val.delay_after_ms);
+                        }
+                        Action::Delay(val) => {
+                            // expressions can only be kept in order while running
+                            if ui.checkbox(&mut val.random, "Random").clicked() {
+                                if let (Some(min), Some(max)) = (
+                                    fixed_value(&mut val.delay_ms_min),
+                                    fixed_value(&mut val.delay_ms_max),
+                                ) {
+                                    *max = (*max).max(*min);
+                                }
                             }
-                            Action::Press(val) => {
-                                egui::ComboBox::new(
-                                    pos + (current_loop_index as usize) * 5000,
-                                    "Key",
-                                )
-                                .selected_text(format!("{}", val.keybutton))
+                            if val.random {
+                                ui.label("Delay min (ms): ");
+                                if add_param(ui, &mut val.delay_ms_min) {
+                                    if let (Some(min), Some(max)) = (
+                                        fixed_value(&mut val.delay_ms_min),
+                                        fixed_value(&mut val.delay_ms_max),
+                                    ) {
+                                        *min = (*min).min(*max);
+                                    }
+                                }
+                                ui.label("Delay max (ms): ");
+                                if add_param(ui, &mut val.delay_ms_max) {
+                                    if let (Some(min), Some(max)) = (
+                                        fixed_value(&mut val.delay_ms_min),
+                                        fixed_value(&mut val.delay_ms_max),
+                                    ) {
+                                        *max = (*max).max(*min);
+                                    }
+                                }
+                                ui.add_space(175.0);
+                            } else {
+                                ui.label("Delay (ms): ");
+                                add_param(ui, &mut val.delay_ms_min);
+                                ui.add_space(345.0);
+                            }
+                        }
+                        Action::SetVariable(val) => {
+                            ui.label("Set ");
+                            ui.add(egui::TextEdit::singleline(&mut val.name).desired_width(80.0));
+                            ui.label(" = ");
+                            add_expression_edit(ui, &mut val.value);
+                        }
+                        Action::Break(val) => {
+                            ui.label("Break ");
+                            add_loop_jump(ui, (current_loop_index, pos), val);
+                        }
+                        Action::Continue(val) => {
+                            ui.label("Continue ");
+                            add_loop_jump(ui, (current_loop_index, pos), val);
+                        }
+                        Action::Call(val) => {
+                            ui.label("Call ");
+                            ui.add(egui::TextEdit::singleline(&mut val.name).desired_width(100.0));
+                            ui.label(" with ");
+                            add_text_list(ui, &mut val.args, |ui, arg| {
+                                add_expression_edit(ui, arg);
+                            });
+                        }
+                        Action::Include(val) => {
+                            ui.label("Include ");
+                            ui.add(egui::TextEdit::singleline(&mut val.path).desired_width(200.0))
+                                .on_hover_text("Path of an .aclick file, relative to this one");
+                        }
+                        Action::Increment(val) => {
+                            ui.label("Increment ");
+                            ui.add(egui::TextEdit::singleline(&mut val.name).desired_width(80.0));
+                            ui.label(" by ");
+                            ui.add(egui::DragValue::new(&mut val.by));
+                        }
+                        Action::Text(val) => {
+                            ui.label("Text: ");
+                            ui.add(egui::TextEdit::singleline(&mut val.text).desired_width(150.0));
+                            ui.add_space(10.0);
+                            ui.label("Char delay (ms): ");
+                            ui.add(egui::DragValue::new(&mut val.char_delay_ms));
+                            ui.add_space(5.0);
+                            ui.label("Jitter (ms): ");
+                            ui.add(egui::DragValue::new(&mut val.jitter_ms));
+                            ui.add_space(7.0);
+                            ui.label("Delay after (ms): ");
+                            add_ranged(ui, &mut val.delay_after_ms);
+                        }
+                        Action::Drag(val) => {
+                            ui.label("from x: ");
+                            add_ranged(ui, &mut val.start_x);
+                            ui.label("y: ");
+                            add_ranged(ui, &mut val.start_y);
+                            ui.add_space(5.0);
+                            ui.label("to x: ");
+                            add_ranged(ui, &mut val.end_x);
+                            ui.label("y: ");
+                            add_ranged(ui, &mut val.end_y);
+                            ui.add_space(5.0);
+                            egui::ComboBox::new((current_loop_index, pos, "button"), "")
+                                .selected_text(format!("{:?}", val.button))
                                 .show_ui(ui, |ui| {
-                                    ui.style_mut().wrap = Some(false);
-                                    ui.set_min_width(60.0);
-
-                                    static ALL_KEYS: [enigo::Key; 247] = [
-                                        enigo::Key::Num0,
-                                        enigo::Key::Num1,
-                                        enigo::Key::Num2,
-                                        enigo::Key::Num3,
-                                        enigo::Key::Num4,
-                                        enigo::Key::Num5,
-                                        enigo::Key::Num6,
-                                        enigo::Key::Num7,
-                                        enigo::Key::Num8,
-                                        enigo::Key::Num9,
-                                        enigo::Key::A,
-                                        enigo::Key::B,
-                                        enigo::Key::C,
-                                        enigo::Key::D,
-                                        enigo::Key::E,
-                                        enigo::Key::F,
-                                        enigo::Key::G,
-                                        enigo::Key::H,
-                                        enigo::Key::I,
-                                        enigo::Key::J,
-                                        enigo::Key::K,
-                                        enigo::Key::L,
-                                        enigo::Key::M,
-                                        enigo::Key::N,
-                                        enigo::Key::O,
-                                        enigo::Key::P,
-                                        enigo::Key::Q,
-                                        enigo::Key::R,
-                                        enigo::Key::S,
-                                        enigo::Key::T,
-                                        enigo::Key::U,
-                                        enigo::Key::V,
-                                        enigo::Key::W,
-                                        enigo::Key::X,
-                                        enigo::Key::Y,
-                                        enigo::Key::Z,
-                                        enigo::Key::AbntC1,
-                                        enigo::Key::AbntC2,
-                                        enigo::Key::Accept,
-                                        enigo::Key::Add,
-                                        enigo::Key::Alt,
-                                        enigo::Key::Apps,
-                                        enigo::Key::Attn,
-                                        enigo::Key::Backspace,
-                                        enigo::Key::BrowserBack,
-                                        enigo::Key::BrowserFavorites,
-                                        enigo::Key::BrowserForward,
-                                        enigo::Key::BrowserHome,
-                                        enigo::Key::BrowserRefresh,
-                                        enigo::Key::BrowserSearch,
-                                        enigo::Key::BrowserStop,
-                                        enigo::Key::Cancel,
-                                        enigo::Key::CapsLock,
-                                        enigo::Key::Clear,
-                                        enigo::Key::Control,
-                                        enigo::Key::Convert,
-                                        enigo::Key::Crsel,
-                                        enigo::Key::DBEAlphanumeric,
-                                        enigo::Key::DBECodeinput,
-                                        enigo::Key::DBEDetermineString,
-                                        enigo::Key::DBEEnterDLGConversionMode,
-                                        enigo::Key::DBEEnterIMEConfigMode,
-                                        enigo::Key::DBEEnterWordRegisterMode,
-                                        enigo::Key::DBEFlushString,
-                                        enigo::Key::DBEHiragana,
-                                        enigo::Key::DBEKatakana,
-                                        enigo::Key::DBENoCodepoint,
-                                        enigo::Key::DBENoRoman,
-                                        enigo::Key::DBERoman,
-                                        enigo::Key::DBESBCSChar,
-                                        enigo::Key::DBESChar,
-                                        enigo::Key::Decimal,
-                                        enigo::Key::Delete,
-                                        enigo::Key::Divide,
-                                        enigo::Key::DownArrow,
-                                        enigo::Key::End,
-                                        enigo::Key::Ereof,
-                                        enigo::Key::Escape,
-                                        enigo::Key::Execute,
-                                        enigo::Key::Exsel,
-                                        enigo::Key::F1,
-                                        enigo::Key::F2,
-                                        enigo::Key::F3,
-                                        enigo::Key::F4,
-                                        enigo::Key::F5,
-                                        enigo::Key::F6,
-                                        enigo::Key::F7,
-                                        enigo::Key::F8,
-                                        enigo::Key::F9,
-                                        enigo::Key::F10,
-                                        enigo::Key::F11,
-                                        enigo::Key::F12,
-                                        enigo::Key::F13,
-                                        enigo::Key::F14,
-                                        enigo::Key::F15,
-                                        enigo::Key::F16,
-                                        enigo::Key::F17,
-                                        enigo::Key::F18,
-                                        enigo::Key::F19,
-                                        enigo::Key::F20,
-                                        enigo::Key::F21,
-                                        enigo::Key::F22,
-                                        enigo::Key::F23,
-                                        enigo::Key::F24,
-                                        enigo::Key::Final,
-                                        enigo::Key::GamepadA,
-                                        enigo::Key::GamepadB,
-                                        enigo::Key::GamepadDPadDown,
-                                        enigo::Key::GamepadDPadLeft,
-                                        enigo::Key::GamepadDPadRight,
-                                        enigo::Key::GamepadDPadUp,
-                                        enigo::Key::GamepadLeftShoulder,
-                                        enigo::Key::GamepadLeftThumbstickButton,
-                                        enigo::Key::GamepadLeftThumbstickDown,
-                                        enigo::Key::GamepadLeftThumbstickLeft,
-                                        enigo::Key::GamepadLeftThumbstickRight,
-                                        enigo::Key::GamepadLeftThumbstickUp,
-                                        enigo::Key::GamepadLeftTrigger,
-                                        enigo::Key::GamepadMenu,
-                                        enigo::Key::GamepadRightShoulder,
-                                        enigo::Key::GamepadRightThumbstickButton,
-                                        enigo::Key::GamepadRightThumbstickDown,
-                                        enigo::Key::GamepadRightThumbstickLeft,
-                                        enigo::Key::GamepadRightThumbstickRight,
-                                        enigo::Key::GamepadRightThumbstickUp,
-                                        enigo::Key::GamepadRightTrigger,
-                                        enigo::Key::GamepadView,
-                                        enigo::Key::GamepadX,
-                                        enigo::Key::GamepadY,
-                                        enigo::Key::Hangeul,
-                                        enigo::Key::Hangul,
-                                        enigo::Key::Hanja,
-                                        enigo::Key::Help,
-                                        enigo::Key::Home,
-                                        enigo::Key::Ico00,
-                                        enigo::Key::IcoClear,
-                                        enigo::Key::IcoHelp,
-                                        enigo::Key::IMEOff,
-                                        enigo::Key::IMEOn,
-                                        enigo::Key::Insert,
-                                        enigo::Key::Junja,
-                                        enigo::Key::Kana,
-                                        enigo::Key::Kanji,
-                                        enigo::Key::LaunchApp1,
-                                        enigo::Key::LaunchApp2,
-                                        enigo::Key::LaunchMail,
-                                        enigo::Key::LaunchMediaSelect,
-                                        enigo::Key::LButton,
-                                        enigo::Key::LControl,
-                                        enigo::Key::LeftArrow,
-                                        enigo::Key::LMenu,
-                                        enigo::Key::LShift,
-                                        enigo::Key::LWin,
-                                        enigo::Key::MButton,
-                                        enigo::Key::MediaNextTrack,
-                                        enigo::Key::MediaPlayPause,
-                                        enigo::Key::MediaPrevTrack,
-                                        enigo::Key::MediaStop,
-                                        // meta key (also known as "windows", "super", and "command")
-                                        enigo::Key::Meta,
-                                        enigo::Key::ModeChange,
-                                        enigo::Key::Multiply,
-                                        enigo::Key::NavigationAccept,
-                                        enigo::Key::NavigationCancel,
-                                        enigo::Key::NavigationDown,
-                                        enigo::Key::NavigationLeft,
-                                        enigo::Key::NavigationMenu,
-                                        enigo::Key::NavigationRight,
-                                        enigo::Key::NavigationUp,
-                                        enigo::Key::NavigationView,
-                                        enigo::Key::NoName,
-                                        enigo::Key::NonConvert,
-                                        enigo::Key::None,
-                                        enigo::Key::Numlock,
-                                        enigo::Key::Numpad0,
-                                        enigo::Key::Numpad1,
-                                        enigo::Key::Numpad2,
-                                        enigo::Key::Numpad3,
-                                        enigo::Key::Numpad4,
-                                        enigo::Key::Numpad5,
-                                        enigo::Key::Numpad6,
-                                        enigo::Key::Numpad7,
-                                        enigo::Key::Numpad8,
-                                        enigo::Key::Numpad9,
-                                        enigo::Key::OEM1,
-                                        enigo::Key::OEM102,
-                                        enigo::Key::OEM2,
-                                        enigo::Key::OEM3,
-                                        enigo::Key::OEM4,
-                                        enigo::Key::OEM5,
-                                        enigo::Key::OEM6,
-                                        enigo::Key::OEM7,
-                                        enigo::Key::OEM8,
-                                        enigo::Key::OEMAttn,
-                                        enigo::Key::OEMAuto,
-                                        enigo::Key::OEMAx,
-                                        enigo::Key::OEMBacktab,
-                                        enigo::Key::OEMClear,
-                                        enigo::Key::OEMComma,
-                                        enigo::Key::OEMCopy,
-                                        enigo::Key::OEMCusel,
-                                        enigo::Key::OEMEnlw,
-                                        enigo::Key::OEMFinish,
-                                        enigo::Key::OEMFJJisho,
-                                        enigo::Key::OEMFJLoya,
-                                        enigo::Key::OEMFJMasshou,
-                                        enigo::Key::OEMFJRoya,
-                                        enigo::Key::OEMFJTouroku,
-                                        enigo::Key::OEMJump,
-                                        enigo::Key::OEMMinus,
-                                        enigo::Key::OEMNECEqual,
-                                        enigo::Key::OEMPA1,
-                                        enigo::Key::OEMPA2,
-                                        enigo::Key::OEMPA3,
-                                        enigo::Key::OEMPeriod,
-                                        enigo::Key::OEMPlus,
-                                        enigo::Key::OEMReset,
-                                        enigo::Key::OEMWsctrl,
-                                        enigo::Key::PA1,
-                                        enigo::Key::Packet,
-                                        enigo::Key::PageDown,
-                                        enigo::Key::PageUp,
-                                        enigo::Key::Pause,
-                                        enigo::Key::Play,
-                                        enigo::Key::Processkey,
-                                        enigo::Key::RButton,
-                                        enigo::Key::RControl,
-                                        enigo::Key::Return,
-                                        enigo::Key::RightArrow,
-                                        enigo::Key::RMenu,
-                                        enigo::Key::RShift,
-                                        enigo::Key::RWin,
-                                        enigo::Key::Scroll,
-                                        enigo::Key::Select,
-                                        enigo::Key::Separator,
-                                        enigo::Key::Shift,
-                                        enigo::Key::Sleep,
-                                        enigo::Key::PrintScr,
-                                        enigo::Key::Space,
-                                        enigo::Key::Subtract,
-                                        enigo::Key::Tab,
-                                        enigo::Key::UpArrow,
-                                        enigo::Key::VolumeDown,
-                                        enigo::Key::VolumeMute,
-                                        enigo::Key::VolumeUp,
-                                        enigo::Key::XButton1,
-                                        enigo::Key::XButton2,
-                                        enigo::Key::Zoom,
-                                    ];
-
-                                    static ALL_BUTTONS: [enigo::Button; 9] = [
-                                        enigo::Button::Left,
-                                        enigo::Button::Middle,
-                                        enigo::Button::Right,
-                                        enigo::Button::Back,
-                                        enigo::Button::Forward,
-                                        enigo::Button::ScrollUp,
-                                        enigo::Button::ScrollDown,
-                                        enigo::Button::ScrollLeft,
-                                        enigo::Button::ScrollRight,
-                                    ];
-
                                     for button in ALL_BUTTONS {
                                         ui.selectable_value(
-                                            &mut val.keybutton,
-                                            button.into(),
+                                            &mut val.button,
+                                            button,
                                             format!("{:?}", button),
                                         );
                                     }
-
-                                    for key in ALL_KEYS {
+                                });
+                            ui.label("Duration (ms): ");
+                            add_ranged(ui, &mut val.duration_ms);
+                            egui::ComboBox::new((current_loop_index, pos, "easing"), "")
+                                .selected_text(format!("{:?}", val.easing))
+                                .show_ui(ui, |ui| {
+                                    for easing in [
+                                        Easing::Linear,
+                                        Easing::EaseIn,
+                                        Easing::EaseOut,
+                                        Easing::EaseInOut,
+                                    ] {
                                         ui.selectable_value(
-                                            &mut val.keybutton,
-                                            key.into(),
-                                            format!("{:?}", key),
+                                            &mut val.easing,
+                                            easing,
+                                            format!("{:?}", easing),
                                         );
                                     }
                                 });
-
-                                ui.add_space(10.0);
-                                ui.vertical(|ui| {
-                                    if ui.checkbox(&mut val.down, "Key-Down").clicked() {
-                                        if !val.down && !val.up {
-                                            val.up = true;
-                                        }
-                                    };
-                                    if ui.checkbox(&mut val.up, "Key-Up").clicked() {
-                                        if !val.down && !val.up {
-                                            val.down = true;
-                                        }
-                                    };
+                            ui.add_space(7.0);
+                            ui.label("Delay after (ms): ");
+                            add_ranged(ui, &mut val.delay_after_ms);
+                        }
+                        Action::ClickAt(val) => {
+                            ui.label("x: ");
+                            ui.add(egui::DragValue::new(&mut val.x));
+                            ui.add_space(10.0);
+                            ui.label("y: ");
+                            ui.add(egui::DragValue::new(&mut val.y));
+                            ui.add_space(5.0);
+                            ui.label("Jitter radius: ");
+                            ui.add(
+                                egui::DragValue::new(&mut val.jitter_radius)
+                                    .clamp_range(0..=MAX_JITTER_RADIUS),
+                            );
+                            ui.add_space(10.0);
+                            egui::ComboBox::new((current_loop_index, pos), "")
+                                .selected_text(format!("{:?}", val.button))
+                                .show_ui(ui, |ui| {
+                                    for button in ALL_BUTTONS {
+                                        ui.selectable_value(
+                                            &mut val.button,
+                                            button,
+                                            format!("{:?}", button),
+                                        );
+                                    }
                                 });
-                                if val.down && val.up {
-                                    ui.label("Hold-Time (ms): ");
-                                    ui.add(egui::DragValue::new(&mut val.hold_time_ms));
-                                    ui.add_space(10.0);
-                                } else {
-                                    ui.add_space(157.0);
-                                }
-                                ui.label("Delay after (ms): ");
-                                ui.add(egui::DragValue::new(&mut val.delay_after_ms));
+                            ui.add(
+                                egui::DragValue::new(&mut val.clicks)
+                                    .clamp_range(1..=3)
+                                    .suffix("x"),
+                            );
+                            if val.clicks > 1 {
+                                ui.label("Interval (ms): ");
+                                ui.add(egui::DragValue::new(&mut val.click_interval_ms));
                             }
-                            Action::Delay(val) => {
-                                if ui.checkbox(&mut val.random, "Random").clicked() {
-                                    if val.delay_ms_max < val.delay_ms_min {
-                                        val.delay_ms_max = val.delay_ms_min;
-                                    }
-                                }
-                                if val.random {
-                                    ui.label("Delay min (ms): ");
-                                    if ui
-                                        .add(egui::DragValue::new(&mut val.delay_ms_min))
-                                        .changed()
-                                    {
-                                        if val.delay_ms_min > val.delay_ms_max {
-                                            val.delay_ms_min = val.delay_ms_max;
-                                        }
-                                    }
-                                    ui.label("Delay max (ms): ");
-                                    if ui
-                                        .add(egui::DragValue::new(&mut val.delay_ms_max))
-                                        .changed()
-                                    {
-                                        if val.delay_ms_max < val.delay_ms_min {
-                                            val.delay_ms_max = val.delay_ms_min;
-                                        }
-                                    }
-                                    ui.add_space(175.0);
-                                } else {
-                                    ui.label("Delay (ms): ");
-                                    ui.add(egui::DragValue::new(&mut val.delay_ms_min));
-                                    ui.add_space(345.0);
+                            ui.add_space(7.0);
+                            ui.label("Delay after (ms): ");
+                            add_ranged(ui, &mut val.delay_after_ms);
+                        }
+                        Action::Scroll(val) => {
+                            egui::ComboBox::new((current_loop_index, pos), "Axis")
+                                .selected_text(format!("{:?}", val.axis))
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(
+                                        &mut val.axis,
+                                        enigo::Axis::Vertical,
+                                        "Vertical",
+                                    );
+                                    ui.selectable_value(
+                                        &mut val.axis,
+                                        enigo::Axis::Horizontal,
+                                        "Horizontal",
+                                    );
+                                });
+                            ui.add_space(10.0);
+                            ui.label("Notches: ");
+                            ui.add(egui::DragValue::new(&mut val.amount))
+                                .on_hover_text("Positive values scroll down or right");
+                            ui.add_space(10.0);
+                            ui.label("Scroll-Time (ms): ");
+                            ui.add(egui::DragValue::new(&mut val.scroll_time_ms));
+                            ui.add_space(7.0);
+                            ui.label("Delay after (ms): ");
+                            add_ranged(ui, &mut val.delay_after_ms);
+                        }
+                        Action::Chord(val) => {
+                            let mut modifier_to_rm: Option<usize> = None;
+                            for (i, modifier) in val.modifiers.iter_mut().enumerate() {
+                                add_keybutton_combo(ui, (current_loop_index, pos, i), "", modifier);
+                                if ui.small_button("x").clicked() {
+                                    modifier_to_rm = Some(i);
                                 }
+                                ui.label("+");
+                            }
+                            if let Some(i) = modifier_to_rm {
+                                val.modifiers.remove(i);
+                            }
+                            add_keybutton_combo(ui, (current_loop_index, pos), "Key", &mut val.key);
+                            if ui.small_button("Add Modifier").clicked() {
+                                val.modifiers.push(enigo::Key::Control.into());
                             }
+                            ui.add_space(10.0);
+                            ui.label("Hold-Time (ms): ");
+                            add_ranged(ui, &mut val.hold_time_ms);
+                            ui.add_space(10.0);
+                            ui.label("Delay after (ms): ");
+                            add_ranged(ui, &mut val.delay_after_ms);
                         }
-                        let trash_icon = egui::include_image!("../assets/trash.svg");
-                        if ui
-                            .add(egui::Button::image_and_text(trash_icon, ""))
-                            .clicked()
+                    }
+                    let trash_icon = egui::include_image!("../assets/trash.svg");
+                    if ui
+                        .add(egui::Button::image_and_text(trash_icon, ""))
+                        .clicked()
+                    {
+                        index_to_rm = Some(pos);
+                    }
+                });
+            });
+        }
+        add_add_buttons(ui, depth, loopaction);
+    });
+
+    if let Some(index) = breakpoint_to_toggle {
+        loopaction.toggle_breakpoint(index);
+    }
+    if let Some(index) = index_to_rm {
+        loopaction.remove_action(index);
+    }
+}
+
+/// The branches are numbered like nested loops, the then branch first,
+/// also while the block is collapsed
+fn add_if_action(
+    ui: &mut Ui,
+    ifaction: &mut IfAction,
+    id_source: (u32, usize),
+    loop_index: &mut u32,
+    depth: u16,
+    progress: &[LoopFrame],
+) {
+    ui.vertical(|ui| {
+        ui.horizontal(|ui| {
+            ui.strong("If");
+            add_condition(ui, id_source, &mut ifaction.condition);
+        });
+        let branches = egui::CollapsingHeader::new(format!(
+            "{} / {} actions",
+            ifaction.then_branch.actions.len(),
+            ifaction.else_branch.actions.len()
+        ))
+        .id_source((id_source, "branches"))
+        .default_open(true)
+        .show(ui, |ui| {
+            for (label, branch) in [
+                ("Then", &mut ifaction.then_branch),
+                ("Else", &mut ifaction.else_branch),
+            ] {
+                *loop_index += 1;
+                let current_loop_index = *loop_index;
+                ui.horizontal(|ui| {
+                    ui.label(label);
+                    ui.separator();
+                    add_action_list(ui, branch, current_loop_index, loop_index, depth, progress);
+                });
+            }
+        });
+        if branches.body_returned.is_none() {
+            *loop_index += ifaction.loop_count();
+        }
+    });
+}
+
+/// The timeout branch is numbered like a nested loop, also while it is hidden
+fn add_wait_until_action(
+    ui: &mut Ui,
+    waitaction: &mut WaitUntilAction,
+    id_source: (u32, usize),
+    loop_index: &mut u32,
+    depth: u16,
+    progress: &[LoopFrame],
+) {
+    ui.vertical(|ui| {
+        ui.horizontal(|ui| {
+            ui.strong("Wait until");
+            add_condition(ui, id_source, &mut waitaction.condition);
+        });
+        ui.horizontal(|ui| {
+            ui.label("Poll every (ms): ");
+            ui.add(
+                egui::DragValue::new(&mut waitaction.poll_interval_ms).clamp_range(1..=u64::MAX),
+            );
+            ui.add_space(10.0);
+            ui.label("Timeout (ms): ");
+            ui.add(egui::DragValue::new(&mut waitaction.timeout_ms))
+                .on_hover_text("0 waits forever");
+            if waitaction.timeout_ms > 0 {
+                egui::ComboBox::new((id_source, "on_timeout"), "")
+                    .selected_text(format!("{:?}", waitaction.on_timeout))
+                    .show_ui(ui, |ui| {
+                        for on_timeout in
+                            [OnTimeout::Continue, OnTimeout::Stop, OnTimeout::RunBranch]
                         {
-                            index_to_rm = Some(pos);
+                            ui.selectable_value(
+                                &mut waitaction.on_timeout,
+                                on_timeout,
+                                format!("{:?}", on_timeout),
+                            );
                         }
                     });
-                });
             }
-            add_add_buttons(ui, depth, loopaction);
         });
+        if waitaction.timeout_ms > 0 && waitaction.on_timeout == OnTimeout::RunBranch {
+            *loop_index += 1;
+            let current_loop_index = *loop_index;
+            ui.horizontal(|ui| {
+                ui.label("On timeout");
+                ui.separator();
+                add_action_list(
+                    ui,
+                    &mut waitaction.timeout_branch,
+                    current_loop_index,
+                    loop_index,
+                    depth,
+                    progress,
+                );
+            });
+        } else {
+            *loop_index += waitaction.timeout_branch.loop_count();
+        }
     });
+}
 
-    if index_to_rm.is_some() {
-        loopaction.actions.remove(index_to_rm.unwrap());
+fn add_loop_jump(ui: &mut Ui, id_source: (u32, usize), jump: &mut LoopJump) {
+    ui.label("loop: ");
+    ui.add(egui::TextEdit::singleline(&mut jump.target).desired_width(80.0))
+        .on_hover_text("Name of the loop, empty for the innermost one");
+    let mut guarded = jump.condition.is_some();
+    if ui.checkbox(&mut guarded, "if").clicked() {
+        jump.condition = guarded.then(|| Condition::Expression("true".to_string()));
+    }
+    if let Some(condition) = &mut jump.condition {
+        add_condition(ui, id_source, condition);
     }
 }
 
-fn add_add_buttons(ui: &mut Ui, depth: u16, loopaction: &mut LoopAction) {
-    ui.horizontal(|ui| {
-        ui.add_space(20.0 * f32::from(depth));
-        if ui.button("Add Key Press").clicked() {
-            loopaction.actions.push(
-                PressAction {
-                    keybutton: enigo::Key::None.into(),
-                    down: true,
-                    up: true,
-                    hold_time_ms: 0,
-                    delay_after_ms: 1,
+fn condition_name(condition: &Condition) -> &'static str {
+    match condition {
+        Condition::PixelColor { .. } => "Pixel",
+        Condition::CursorIn { .. } => "Cursor in",
+        Condition::Elapsed { .. } => "Elapsed",
+        Condition::Expression(_) => "Expression",
+    }
+}
+
+fn add_condition(ui: &mut Ui, id_source: (u32, usize), condition: &mut Condition) {
+    let kinds = [
+        DEFAULT_CONDITION,
+        Condition::CursorIn {
+            x: 0,
+            y: 0,
+            width: 100,
+            height: 100,
+        },
+        Condition::Elapsed { ms: 1000 },
+        Condition::Expression("iteration % 10 == 0".to_string()),
+    ];
+    egui::ComboBox::new((id_source, "condition"), "")
+        .selected_text(condition_name(condition))
+        .show_ui(ui, |ui| {
+            for kind in kinds {
+                let selected = std::mem::discriminant(condition) == std::mem::discriminant(&kind);
+                if ui
+                    .selectable_label(selected, condition_name(&kind))
+                    .clicked()
+                    && !selected
+                {
+                    *condition = kind;
                 }
-                .into(),
-            );
+            }
+        });
+    match condition {
+        Condition::PixelColor {
+            x,
+            y,
+            radius,
+            color,
+            tolerance,
+        } => {
+            ui.label("pixel at x: ");
+            ui.add(egui::DragValue::new(x));
+            ui.label("y: ");
+            ui.add(egui::DragValue::new(y));
+            ui.label("radius: ");
+            ui.add(egui::DragValue::new(radius).clamp_range(0..=MAX_RADIUS));
+            ui.label("is ");
+            let mut rgb = [color.r, color.g, color.b];
+            if ui.color_edit_button_srgb(&mut rgb).changed() {
+                *color = Color::rgb(rgb[0], rgb[1], rgb[2]);
+            }
+            ui.label("± ");
+            ui.add(egui::DragValue::new(tolerance))
+                .on_hover_text("How far each channel may be off");
         }
-        if ui.button("Add Mouse Move").clicked() {
-            loopaction.actions.push(
-                MoveAction {
-                    x: 0,
-                    y: 0,
-                    relative: false,
-                    move_time_ms: 0,
-                    delay_after_ms: 1,
-                }
-                .into(),
-            )
+        Condition::CursorIn {
+            x,
+            y,
+            width,
+            height,
+        } => {
+            ui.label("cursor in x: ");
+            ui.add(egui::DragValue::new(x));
+            ui.label("y: ");
+            ui.add(egui::DragValue::new(y));
+            ui.label("width: ");
+            ui.add(egui::DragValue::new(width));
+            ui.label("height: ");
+            ui.add(egui::DragValue::new(height));
         }
-        if ui.button("Add Delay").clicked() {
-            loopaction.actions.push(
-                DelayAction {
-                    random: false,
-                    delay_ms_min: 1,
-                    delay_ms_max: 2,
-                }
-                .into(),
-            )
+        Condition::Elapsed { ms } => {
+            ui.label("since start (ms): ");
+            ui.add(egui::DragValue::new(ms));
+        }
+        Condition::Expression(source) => {
+            add_expression_edit(ui, source);
+        }
+    }
+}
+
+// enigo only exposes the virtual key codes on Windows, other
+// platforms get the keys every backend supports
+#[cfg(not(target_os = "windows"))]
+static ALL_KEYS: [enigo::Key; 101] = [
+    enigo::Key::Unicode('0'),
+    enigo::Key::Unicode('1'),
+    enigo::Key::Unicode('2'),
+    enigo::Key::Unicode('3'),
+    enigo::Key::Unicode('4'),
+    enigo::Key::Unicode('5'),
+    enigo::Key::Unicode('6'),
+    enigo::Key::Unicode('7'),
+    enigo::Key::Unicode('8'),
+    enigo::Key::Unicode('9'),
+    enigo::Key::Unicode('a'),
+    enigo::Key::Unicode('b'),
+    enigo::Key::Unicode('c'),
+    enigo::Key::Unicode('d'),
+    enigo::Key::Unicode('e'),
+    enigo::Key::Unicode('f'),
+    enigo::Key::Unicode('g'),
+    enigo::Key::Unicode('h'),
+    enigo::Key::Unicode('i'),
+    enigo::Key::Unicode('j'),
+    enigo::Key::Unicode('k'),
+    enigo::Key::Unicode('l'),
+    enigo::Key::Unicode('m'),
+    enigo::Key::Unicode('n'),
+    enigo::Key::Unicode('o'),
+    enigo::Key::Unicode('p'),
+    enigo::Key::Unicode('q'),
+    enigo::Key::Unicode('r'),
+    enigo::Key::Unicode('s'),
+    enigo::Key::Unicode('t'),
+    enigo::Key::Unicode('u'),
+    enigo::Key::Unicode('v'),
+    enigo::Key::Unicode('w'),
+    enigo::Key::Unicode('x'),
+    enigo::Key::Unicode('y'),
+    enigo::Key::Unicode('z'),
+    enigo::Key::Add,
+    enigo::Key::Alt,
+    enigo::Key::Backspace,
+    enigo::Key::CapsLock,
+    enigo::Key::Control,
+    enigo::Key::Decimal,
+    enigo::Key::Delete,
+    enigo::Key::Divide,
+    enigo::Key::DownArrow,
+    enigo::Key::End,
+    enigo::Key::Escape,
+    enigo::Key::F1,
+    enigo::Key::F2,
+    enigo::Key::F3,
+    enigo::Key::F4,
+    enigo::Key::F5,
+    enigo::Key::F6,
+    enigo::Key::F7,
+    enigo::Key::F8,
+    enigo::Key::F9,
+    enigo::Key::F10,
+    enigo::Key::F11,
+    enigo::Key::F12,
+    enigo::Key::F13,
+    enigo::Key::F14,
+    enigo::Key::F15,
+    enigo::Key::F16,
+    enigo::Key::F17,
+    enigo::Key::F18,
+    enigo::Key::F19,
+    enigo::Key::F20,
+    enigo::Key::Help,
+    enigo::Key::Home,
+    enigo::Key::LControl,
+    enigo::Key::LeftArrow,
+    enigo::Key::LShift,
+    enigo::Key::MediaNextTrack,
+    enigo::Key::MediaPlayPause,
+    enigo::Key::MediaPrevTrack,
+    enigo::Key::Meta,
+    enigo::Key::Multiply,
+    enigo::Key::Numpad0,
+    enigo::Key::Numpad1,
+    enigo::Key::Numpad2,
+    enigo::Key::Numpad3,
+    enigo::Key::Numpad4,
+    enigo::Key::Numpad5,
+    enigo::Key::Numpad6,
+    enigo::Key::Numpad7,
+    enigo::Key::Numpad8,
+    enigo::Key::Numpad9,
+    enigo::Key::PageDown,
+    enigo::Key::PageUp,
+    enigo::Key::RControl,
+    enigo::Key::Return,
+    enigo::Key::RightArrow,
+    enigo::Key::RShift,
+    enigo::Key::Shift,
+    enigo::Key::Space,
+    enigo::Key::Subtract,
+    enigo::Key::Tab,
+    enigo::Key::UpArrow,
+    enigo::Key::VolumeDown,
+    enigo::Key::VolumeMute,
+    enigo::Key::VolumeUp,
+];
+
+#[cfg(target_os = "windows")]
+static ALL_KEYS: [enigo::Key; 247] = [
+    enigo::Key::Num0,
+    enigo::Key::Num1,
+    enigo::Key::Num2,
+    enigo::Key::Num3,
+    enigo::Key::Num4,
+    enigo::Key::Num5,
+    enigo::Key::Num6,
+    enigo::Key::Num7,
+    enigo::Key::Num8,
+    enigo::Key::Num9,
+    enigo::Key::A,
+    enigo::Key::B,
+    enigo::Key::C,
+    enigo::Key::D,
+    enigo::Key::E,
+    enigo::Key::F,
+    enigo::Key::G,
+    enigo::Key::H,
+    enigo::Key::I,
+    enigo::Key::J,
+    enigo::Key::K,
+    enigo::Key::L,
+    enigo::Key::M,
+    enigo::Key::N,
+    enigo::Key::O,
+    enigo::Key::P,
+    enigo::Key::Q,
+    enigo::Key::R,
+    enigo::Key::S,
+    enigo::Key::T,
+    enigo::Key::U,
+    enigo::Key::V,
+    enigo::Key::W,
+    enigo::Key::X,
+    enigo::Key::Y,
+    enigo::Key::Z,
+    enigo::Key::AbntC1,
+    enigo::Key::AbntC2,
+    enigo::Key::Accept,
+    enigo::Key::Add,
+    enigo::Key::Alt,
+    enigo::Key::Apps,
+    enigo::Key::Attn,
+    enigo::Key::Backspace,
+    enigo::Key::BrowserBack,
+    enigo::Key::BrowserFavorites,
+    enigo::Key::BrowserForward,
+    enigo::Key::BrowserHome,
+    enigo::Key::BrowserRefresh,
+    enigo::Key::BrowserSearch,
+    enigo::Key::BrowserStop,
+    enigo::Key::Cancel,
+    enigo::Key::CapsLock,
+    enigo::Key::Clear,
+    enigo::Key::Control,
+    enigo::Key::Convert,
+    enigo::Key::Crsel,
+    enigo::Key::DBEAlphanumeric,
+    enigo::Key::DBECodeinput,
+    enigo::Key::DBEDetermineString,
+    enigo::Key::DBEEnterDLGConversionMode,
+    enigo::Key::DBEEnterIMEConfigMode,
+    enigo::Key::DBEEnterWordRegisterMode,
+    enigo::Key::DBEFlushString,
+    enigo::Key::DBEHiragana,
+    enigo::Key::DBEKatakana,
+    enigo::Key::DBENoCodepoint,
+    enigo::Key::DBENoRoman,
+    enigo::Key::DBERoman,
+    enigo::Key::DBESBCSChar,
+    enigo::Key::DBESChar,
+    enigo::Key::Decimal,
+    enigo::Key::Delete,
+    enigo::Key::Divide,
+    enigo::Key::DownArrow,
+    enigo::Key::End,
+    enigo::Key::Ereof,
+    enigo::Key::Escape,
+    enigo::Key::Execute,
+    enigo::Key::Exsel,
+    enigo::Key::F1,
+    enigo::Key::F2,
+    enigo::Key::F3,
+    enigo::Key::F4,
+    enigo::Key::F5,
+    enigo::Key::F6,
+    enigo::Key::F7,
+    enigo::Key::F8,
+    enigo::Key::F9,
+    enigo::Key::F10,
+    enigo::Key::F11,
+    enigo::Key::F12,
+    enigo::Key::F13,
+    enigo::Key::F14,
+    enigo::Key::F15,
+    enigo::Key::F16,
+    enigo::Key::F17,
+    enigo::Key::F18,
+    enigo::Key::F19,
+    enigo::Key::F20,
+    enigo::Key::F21,
+    enigo::Key::F22,
+    enigo::Key::F23,
+    enigo::Key::F24,
+    enigo::Key::Final,
+    enigo::Key::GamepadA,
+    enigo::Key::GamepadB,
+    enigo::Key::GamepadDPadDown,
+    enigo::Key::GamepadDPadLeft,
+    enigo::Key::GamepadDPadRight,
+    enigo::Key::GamepadDPadUp,
+    enigo::Key::GamepadLeftShoulder,
+    enigo::Key::GamepadLeftThumbstickButton,
+    enigo::Key::GamepadLeftThumbstickDown,
+    enigo::Key::GamepadLeftThumbstickLeft,
+    enigo::Key::GamepadLeftThumbstickRight,
+    enigo::Key::GamepadLeftThumbstickUp,
+    enigo::Key::GamepadLeftTrigger,
+    enigo::Key::GamepadMenu,
+    enigo::Key::GamepadRightShoulder,
+    enigo::Key::GamepadRightThumbstickButton,
+    enigo::Key::GamepadRightThumbstickDown,
+    enigo::Key::GamepadRightThumbstickLeft,
+    enigo::Key::GamepadRightThumbstickRight,
+    enigo::Key::GamepadRightThumbstickUp,
+    enigo::Key::GamepadRightTrigger,
+    enigo::Key::GamepadView,
+    enigo::Key::GamepadX,
+    enigo::Key::GamepadY,
+    enigo::Key::Hangeul,
+    enigo::Key::Hangul,
+    enigo::Key::Hanja,
+    enigo::Key::Help,
+    enigo::Key::Home,
+    enigo::Key::Ico00,
+    enigo::Key::IcoClear,
+    enigo::Key::IcoHelp,
+    enigo::Key::IMEOff,
+    enigo::Key::IMEOn,
+    enigo::Key::Insert,
+    enigo::Key::Junja,
+    enigo::Key::Kana,
+    enigo::Key::Kanji,
+    enigo::Key::LaunchApp1,
+    enigo::Key::LaunchApp2,
+    enigo::Key::LaunchMail,
+    enigo::Key::LaunchMediaSelect,
+    enigo::Key::LButton,
+    enigo::Key::LControl,
+    enigo::Key::LeftArrow,
+    enigo::Key::LMenu,
+    enigo::Key::LShift,
+    enigo::Key::LWin,
+    enigo::Key::MButton,
+    enigo::Key::MediaNextTrack,
+    enigo::Key::MediaPlayPause,
+    enigo::Key::MediaPrevTrack,
+    enigo::Key::MediaStop,
+    // meta key (also known as "windows", "super", and "command")
+    enigo::Key::Meta,
+    enigo::Key::ModeChange,
+    enigo::Key::Multiply,
+    enigo::Key::NavigationAccept,
+    enigo::Key::NavigationCancel,
+    enigo::Key::NavigationDown,
+    enigo::Key::NavigationLeft,
+    enigo::Key::NavigationMenu,
+    enigo::Key::NavigationRight,
+    enigo::Key::NavigationUp,
+    enigo::Key::NavigationView,
+    enigo::Key::NoName,
+    enigo::Key::NonConvert,
+    enigo::Key::None,
+    enigo::Key::Numlock,
+    enigo::Key::Numpad0,
+    enigo::Key::Numpad1,
+    enigo::Key::Numpad2,
+    enigo::Key::Numpad3,
+    enigo::Key::Numpad4,
+    enigo::Key::Numpad5,
+    enigo::Key::Numpad6,
+    enigo::Key::Numpad7,
+    enigo::Key::Numpad8,
+    enigo::Key::Numpad9,
+    enigo::Key::OEM1,
+    enigo::Key::OEM102,
+    enigo::Key::OEM2,
+    enigo::Key::OEM3,
+    enigo::Key::OEM4,
+    enigo::Key::OEM5,
+    enigo::Key::OEM6,
+    enigo::Key::OEM7,
+    enigo::Key::OEM8,
+    enigo::Key::OEMAttn,
+    enigo::Key::OEMAuto,
+    enigo::Key::OEMAx,
+    enigo::Key::OEMBacktab,
+    enigo::Key::OEMClear,
+    enigo::Key::OEMComma,
+    enigo::Key::OEMCopy,
+    enigo::Key::OEMCusel,
+    enigo::Key::OEMEnlw,
+    enigo::Key::OEMFinish,
+    enigo::Key::OEMFJJisho,
+    enigo::Key::OEMFJLoya,
+    enigo::Key::OEMFJMasshou,
+    enigo::Key::OEMFJRoya,
+    enigo::Key::OEMFJTouroku,
+    enigo::Key::OEMJump,
+    enigo::Key::OEMMinus,
+    enigo::Key::OEMNECEqual,
+    enigo::Key::OEMPA1,
+    enigo::Key::OEMPA2,
+    enigo::Key::OEMPA3,
+    enigo::Key::OEMPeriod,
+    enigo::Key::OEMPlus,
+    enigo::Key::OEMReset,
+    enigo::Key::OEMWsctrl,
+    enigo::Key::PA1,
+    enigo::Key::Packet,
+    enigo::Key::PageDown,
+    enigo::Key::PageUp,
+    enigo::Key::Pause,
+    enigo::Key::Play,
+    enigo::Key::Processkey,
+    enigo::Key::RButton,
+    enigo::Key::RControl,
+    enigo::Key::Return,
+    enigo::Key::RightArrow,
+    enigo::Key::RMenu,
+    enigo::Key::RShift,
+    enigo::Key::RWin,
+    enigo::Key::Scroll,
+    enigo::Key::Select,
+    enigo::Key::Separator,
+    enigo::Key::Shift,
+    enigo::Key::Sleep,
+    enigo::Key::PrintScr,
+    enigo::Key::Space,
+    enigo::Key::Subtract,
+    enigo::Key::Tab,
+    enigo::Key::UpArrow,
+    enigo::Key::VolumeDown,
+    enigo::Key::VolumeMute,
+    enigo::Key::VolumeUp,
+    enigo::Key::XButton1,
+    enigo::Key::XButton2,
+    enigo::Key::Zoom,
+];
+
+static ALL_BUTTONS: [enigo::Button; 9] = [
+    enigo::Button::Left,
+    enigo::Button::Middle,
+    enigo::Button::Right,
+    enigo::Button::Back,
+    enigo::Button::Forward,
+    enigo::Button::ScrollUp,
+    enigo::Button::ScrollDown,
+    enigo::Button::ScrollLeft,
+    enigo::Button::ScrollRight,
+];
+
+fn add_time_limit(
+    ui: &mut Ui,
+    id_source: impl std::hash::Hash,
+    time_limit: &mut Option<TimeLimit>,
+) {
+    let is_for = matches!(time_limit, Some(TimeLimit::For { .. }));
+    let is_until = matches!(time_limit, Some(TimeLimit::Until { .. }));
+    egui::ComboBox::new(id_source, "")
+        .selected_text(if is_for {
+            "Run for"
+        } else if is_until {
+            "Run until"
+        } else {
+            "No time limit"
+        })
+        .show_ui(ui, |ui| {
+            if ui
+                .selectable_label(time_limit.is_none(), "No time limit")
+                .clicked()
+            {
+                *time_limit = None;
+            }
+            if ui.selectable_label(is_for, "Run for").clicked() && !is_for {
+                *time_limit = Some(TimeLimit::For { secs: 60 });
+            }
+            if ui.selectable_label(is_until, "Run until").clicked() && !is_until {
+                *time_limit = Some(TimeLimit::Until {
+                    hour: 12,
+                    minute: 0,
+                    utc_offset_minutes: None,
+                });
+            }
+        });
+    match time_limit {
+        Some(TimeLimit::For { secs }) => {
+            ui.horizontal(|ui| {
+                let mut minutes = *secs / 60;
+                let mut seconds = *secs % 60;
+                ui.add(egui::DragValue::new(&mut minutes).suffix(" min"));
+                ui.add(
+                    egui::DragValue::new(&mut seconds)
+                        .clamp_range(0..=59)
+                        .suffix(" s"),
+                );
+                *secs = minutes * 60 + seconds;
+            });
         }
-        if ui.button("Add Loop").clicked() {
-            loopaction.actions.push(
-                LoopAction {
-                    infinite: false,
-                    iterations: 1,
-                    actions: Vec::new(),
+        Some(TimeLimit::Until {
+            hour,
+            minute,
+            utc_offset_minutes,
+        }) => {
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(hour).clamp_range(0..=23));
+                ui.label(":");
+                ui.add(egui::DragValue::new(minute).clamp_range(0..=59));
+            });
+            ui.horizontal(|ui| {
+                let mut fixed_offset = utc_offset_minutes.is_some();
+                if ui
+                    .checkbox(&mut fixed_offset, "UTC offset (min): ")
+                    .on_hover_text("Uses the local time zone of the system if unchecked")
+                    .clicked()
+                {
+                    *utc_offset_minutes = fixed_offset.then_some(0);
                 }
-                .into(),
-            )
+                if let Some(utc_offset_minutes) = utc_offset_minutes {
+                    ui.add(egui::DragValue::new(utc_offset_minutes).clamp_range(-720..=840));
+                }
+            });
+        }
+        None => {}
+    }
+}
+
+/// Number that can be randomized, the variation is picked from a menu next to it
+fn add_ranged<T: Number + egui::emath::Numeric>(ui: &mut Ui, ranged: &mut Ranged<T>) {
+    ui.add(egui::DragValue::new(&mut ranged.value));
+    match ranged.variation {
+        Variation::Fixed => {}
+        Variation::Jitter => {
+            ui.label("±");
+            ui.add(egui::DragValue::new(&mut ranged.amount));
+        }
+        Variation::Range => {
+            ui.label("to");
+            ui.add(egui::DragValue::new(&mut ranged.amount));
+        }
+    }
+    ui.menu_button("~", |ui| {
+        ui.radio_value(&mut ranged.variation, Variation::Fixed, "Fixed");
+        ui.radio_value(&mut ranged.variation, Variation::Jitter, "Jitter (±)");
+        ui.radio_value(&mut ranged.variation, Variation::Range, "Range");
+        ui.add_enabled(
+            ranged.variation != Variation::Fixed,
+            egui::Checkbox::new(&mut ranged.gaussian, "Gaussian"),
+        );
+    })
+    .response
+    .on_hover_text("Randomize");
+}
+
+fn fixed_value(param: &mut Param<u64>) -> Option<&mut u64> {
+    match param {
+        Param::Ranged(ranged) => Some(&mut ranged.value),
+        Param::Expr(_) => None,
+    }
+}
+
+/// Returns whether the value was changed
+fn add_param<T: Number + egui::emath::Numeric>(ui: &mut Ui, param: &mut Param<T>) -> bool {
+    let mut changed = match param {
+        Param::Ranged(ranged) => {
+            let before = *ranged;
+            add_ranged(ui, ranged);
+            *ranged != before
         }
+        Param::Expr(source) => add_expression_edit(ui, source),
+    };
+    let is_expr = matches!(param, Param::Expr(_));
+    if ui
+        .selectable_label(is_expr, "x=")
+        .on_hover_text("Compute from variables, e.g. 100 + i * 40")
+        .clicked()
+    {
+        *param = match param {
+            Param::Ranged(ranged) => Param::Expr(Number::to_f64(ranged.value).to_string()),
+            Param::Expr(source) => source
+                .trim()
+                .parse()
+                .map(|value| <T as Number>::from_f64(value).into())
+                .unwrap_or_default(),
+        };
+        changed = true;
+    }
+    changed
+}
+
+/// Shows the expression in red while it does not parse.
+/// Returns whether it was changed.
+fn add_expression_edit(ui: &mut Ui, source: &mut String) -> bool {
+    let error = Expr::parse(source).err();
+    let mut edit = egui::TextEdit::singleline(source).desired_width(100.0);
+    if error.is_some() {
+        edit = edit.text_color(Color32::RED);
+    }
+    let response = ui.add(edit);
+    let response = match error {
+        Some(err) => response.on_hover_text(err.to_string()),
+        None => response,
+    };
+    response.changed()
+}
+
+fn add_keybutton_combo(
+    ui: &mut Ui,
+    id_source: impl std::hash::Hash,
+    label: &str,
+    keybutton: &mut KeyButton,
+) {
+    egui::ComboBox::new(id_source, label)
+        .selected_text(format!("{}", keybutton))
+        .show_ui(ui, |ui| {
+            ui.style_mut().wrap = Some(false);
+            ui.set_min_width(60.0);
+
+            for button in ALL_BUTTONS {
+                ui.selectable_value(keybutton, button.into(), format!("{:?}", button));
+            }
+
+            for key in ALL_KEYS {
+                ui.selectable_value(keybutton, key.into(), format!("{:?}", key));
+            }
+        });
+}
+
+#[cfg(target_os = "windows")]
+const DEFAULT_KEY: enigo::Key = enigo::Key::None;
+#[cfg(not(target_os = "windows"))]
+const DEFAULT_KEY: enigo::Key = enigo::Key::Space;
+
+const DEFAULT_CONDITION: Condition = Condition::PixelColor {
+    x: 0,
+    y: 0,
+    radius: 0,
+    color: Color::rgb(255, 255, 255),
+    tolerance: 10,
+};
+
+/// Larger click jitter would only ever land off screen
+const MAX_JITTER_RADIUS: u32 = 1000;
+
+fn add_add_buttons(ui: &mut Ui, depth: u16, loopaction: &mut LoopAction) {
+    ui.horizontal(|ui| {
+        ui.add_space(20.0 * f32::from(depth));
+        // wraps, one button per kind of action is more than fits into a row
+        ui.horizontal_wrapped(|ui| {
+            if ui.button("Add Key Press").clicked() {
+                loopaction.actions.push(
+                    PressAction {
+                        keybutton: DEFAULT_KEY.into(),
+                        down: true,
+                        up: true,
+                        hold_time_ms: 0.into(),
+                        delay_after_ms: 1.into(),
+                    }
+                    .into(),
+                );
+            }
+            if ui.button("Add Key Chord").clicked() {
+                loopaction.actions.push(
+                    ChordAction {
+                        modifiers: vec![enigo::Key::Control.into()],
+                        key: DEFAULT_KEY.into(),
+                        hold_time_ms: 0.into(),
+                        delay_after_ms: 1.into(),
+                    }
+                    .into(),
+                );
+            }
+            if ui.button("Add Mouse Move").clicked() {
+                loopaction.actions.push(
+                    MoveAction {
+                        x: 0.into(),
+                        y: 0.into(),
+                        relative: false,
+                        move_time_ms: 0.into(),
+                        delay_after_ms: 1.into(),
+                        path: MovePath::default(),
+                    }
+                    .into(),
+                )
+            }
+            if ui.button("Add Click At").clicked() {
+                loopaction.actions.push(
+                    ClickAtAction {
+                        x: 0,
+                        y: 0,
+                        button: enigo::Button::Left,
+                        clicks: 1,
+                        click_interval_ms: 50,
+                        jitter_radius: 0,
+                        delay_after_ms: 1.into(),
+                    }
+                    .into(),
+                )
+            }
+            if ui.button("Add Drag").clicked() {
+                loopaction.actions.push(
+                    DragAction {
+                        start_x: 0.into(),
+                        start_y: 0.into(),
+                        end_x: 100.into(),
+                        end_y: 0.into(),
+                        button: enigo::Button::Left,
+                        duration_ms: 200.into(),
+                        easing: Easing::EaseInOut,
+                        delay_after_ms: 1.into(),
+                    }
+                    .into(),
+                )
+            }
+            if ui.button("Add Scroll").clicked() {
+                loopaction.actions.push(
+                    ScrollAction {
+                        axis: enigo::Axis::Vertical,
+                        amount: 1,
+                        scroll_time_ms: 0,
+                        delay_after_ms: 1.into(),
+                    }
+                    .into(),
+                )
+            }
+            if ui.button("Add Delay").clicked() {
+                loopaction.actions.push(
+                    DelayAction {
+                        random: false,
+                        delay_ms_min: 1.into(),
+                        delay_ms_max: 2.into(),
+                    }
+                    .into(),
+                )
+            }
+            if ui.button("Add Text").clicked() {
+                loopaction.actions.push(
+                    TextAction {
+                        text: String::new(),
+                        char_delay_ms: 20,
+                        jitter_ms: 0,
+                        delay_after_ms: 1.into(),
+                    }
+                    .into(),
+                )
+            }
+            if ui.button("Add Wait Until").clicked() {
+                loopaction
+                    .actions
+                    .push(WaitUntilAction::new(DEFAULT_CONDITION).into())
+            }
+            if ui.button("Add If").clicked() {
+                loopaction.actions.push(
+                    IfAction::new(Condition::Expression("iteration % 10 == 0".to_string())).into(),
+                )
+            }
+            if ui.button("Add Set Variable").clicked() {
+                loopaction.actions.push(
+                    SetVariableAction {
+                        name: "x".to_string(),
+                        value: "0".to_string(),
+                    }
+                    .into(),
+                )
+            }
+            if ui.button("Add Increment").clicked() {
+                loopaction.actions.push(
+                    IncrementAction {
+                        name: "x".to_string(),
+                        by: 1,
+                    }
+                    .into(),
+                )
+            }
+            if ui.button("Add Call").clicked() {
+                loopaction.actions.push(
+                    CallAction {
+                        name: "procedure1".to_string(),
+                        args: Vec::new(),
+                    }
+                    .into(),
+                )
+            }
+            if ui.button("Add Include").clicked() {
+                loopaction.actions.push(
+                    IncludeAction {
+                        path: "other.aclick".to_string(),
+                    }
+                    .into(),
+                )
+            }
+            if ui.button("Add Break").clicked() {
+                loopaction.actions.push(Action::Break(LoopJump::default()))
+            }
+            if ui.button("Add Continue").clicked() {
+                loopaction
+                    .actions
+                    .push(Action::Continue(LoopJump::default()))
+            }
+            if ui.button("Add Loop").clicked() {
+                loopaction.actions.push(
+                    LoopAction {
+                        name: String::new(),
+                        infinite: false,
+                        iterations: 1.into(),
+                        actions: Vec::new(),
+                        breakpoints: Vec::new(),
+                        time_limit: None,
+                        procedures: Vec::new(),
+                    }
+                    .into(),
+                )
+            }
+        });
     });
 }
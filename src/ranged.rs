@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+
+/// A number of an action that can vary every time the action runs, so
+/// macros don't produce a perfectly periodic pattern.
+///
+/// Serialized as a plain number while it is fixed, so files written
+/// before the variation existed still load.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(
+    from = "RangedRepr<T>",
+    into = "RangedRepr<T>",
+    bound(
+        serialize = "T: Number + Serialize",
+        deserialize = "T: Number + Deserialize<'de>"
+    )
+)]
+pub struct Ranged<T: Number> {
+    pub value: T,
+    pub variation: Variation,
+    /// The `±` amount of a jitter, or the upper bound of a range
+    pub amount: T,
+    /// Picks values close to the middle more often than at the edges
+    pub gaussian: bool,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Variation {
+    /// Always `value`
+    #[default]
+    Fixed,
+    /// Between `value - amount` and `value + amount`
+    Jitter,
+    /// Between `value` and `amount`
+    Range,
+}
+
+impl<T: Number> Ranged<T> {
+    pub fn fixed(value: T) -> Self {
+        Self {
+            value,
+            variation: Variation::Fixed,
+            amount: T::default(),
+            gaussian: false,
+        }
+    }
+
+    pub fn jitter(value: T, amount: T) -> Self {
+        Self {
+            variation: Variation::Jitter,
+            amount,
+            ..Self::fixed(value)
+        }
+    }
+
+    pub fn range(min: T, max: T) -> Self {
+        Self {
+            variation: Variation::Range,
+            amount: max,
+            ..Self::fixed(min)
+        }
+    }
+
+    /// Smallest and largest value it can take
+    pub fn bounds(&self) -> (T, T) {
+        let value = self.value.to_f64();
+        let amount = self.amount.to_f64();
+        let (min, max) = match self.variation {
+            Variation::Fixed => (value, value),
+            Variation::Jitter => (value - amount.abs(), value + amount.abs()),
+            Variation::Range => (value.min(amount), value.max(amount)),
+        };
+        (T::from_f64(min), T::from_f64(max))
+    }
+
+    /// Picks the value for one run of the action
+    pub fn sample(&self, rng: &mut fastrand::Rng) -> T {
+        let (min, max) = self.bounds();
+        let (min, max) = (min.to_f64(), max.to_f64());
+        if min == max {
+            return self.value;
+        }
+        let value = if self.gaussian {
+            // Box-Muller, 99.7% of the values are within the bounds
+            let mean = (min + max) / 2.0;
+            let std_dev = (max - min) / 6.0;
+            let u1 = 1.0 - rng.f64();
+            let u2 = rng.f64();
+            let normal = (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos();
+            (mean + normal * std_dev).clamp(min, max)
+        } else {
+            min + rng.f64() * (max - min)
+        };
+        T::from_f64(value)
+    }
+}
+
+impl<T: Number> From<T> for Ranged<T> {
+    fn from(value: T) -> Self {
+        Self::fixed(value)
+    }
+}
+
+/// Numbers a [`Ranged`] can hold
+pub trait Number: Copy + PartialEq + Default {
+    fn to_f64(self) -> f64;
+    /// Rounds to the closest representable number
+    fn from_f64(value: f64) -> Self;
+    /// `None` if the value is out of range
+    fn from_i64(value: i64) -> Option<Self>;
+}
+
+impl Number for u64 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+    fn from_f64(value: f64) -> Self {
+        value.round().max(0.0) as u64
+    }
+    fn from_i64(value: i64) -> Option<Self> {
+        u64::try_from(value).ok()
+    }
+}
+
+impl Number for i32 {
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+    fn from_f64(value: f64) -> Self {
+        value.round() as i32
+    }
+    fn from_i64(value: i64) -> Option<Self> {
+        i32::try_from(value).ok()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum RangedRepr<T> {
+    Fixed(T),
+    Varying {
+        value: T,
+        variation: Variation,
+        amount: T,
+        #[serde(default)]
+        gaussian: bool,
+    },
+}
+
+impl<T: Number> From<RangedRepr<T>> for Ranged<T> {
+    fn from(repr: RangedRepr<T>) -> Self {
+        match repr {
+            RangedRepr::Fixed(value) => Self::fixed(value),
+            RangedRepr::Varying {
+                value,
+                variation,
+                amount,
+                gaussian,
+            } => Self {
+                value,
+                variation,
+                amount,
+                gaussian,
+            },
+        }
+    }
+}
+
+impl<T: Number> From<Ranged<T>> for RangedRepr<T> {
+    fn from(ranged: Ranged<T>) -> Self {
+        match ranged.variation {
+            Variation::Fixed => RangedRepr::Fixed(ranged.value),
+            variation => RangedRepr::Varying {
+                value: ranged.value,
+                variation,
+                amount: ranged.amount,
+                gaussian: ranged.gaussian,
+            },
+        }
+    }
+}
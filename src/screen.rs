@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+use crate::errors::AppError;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Whether no channel differs by more than `tolerance`
+    pub fn matches(self, other: Color, tolerance: u8) -> bool {
+        self.r.abs_diff(other.r) <= tolerance
+            && self.g.abs_diff(other.g) <= tolerance
+            && self.b.abs_diff(other.b) <= tolerance
+    }
+}
+
+/// Read access to what is currently shown on the screen.
+pub trait Screen {
+    fn pixel(&self, x: i32, y: i32) -> Result<Color, AppError>;
+
+    /// Average color of the square reaching `radius` pixels around `x`, `y`
+    fn average(&self, x: i32, y: i32, radius: u32) -> Result<Color, AppError> {
+        average_of(x, y, radius, |x, y| self.pixel(x, y))
+    }
+}
+
+/// Largest radius [`Screen::average`] accepts
+pub const MAX_RADIUS: u32 = 20;
+
+/// Averages what `pixel` reads in the square reaching `radius` pixels around `x`, `y`
+fn average_of(
+    x: i32,
+    y: i32,
+    radius: u32,
+    mut pixel: impl FnMut(i32, i32) -> Result<Color, AppError>,
+) -> Result<Color, AppError> {
+    if radius > MAX_RADIUS {
+        return Err(AppError::Screen(format!(
+            "radius {} is larger than {}",
+            radius, MAX_RADIUS
+        )));
+    }
+    let radius = radius as i32;
+    let (mut r, mut g, mut b, mut count) = (0u64, 0u64, 0u64, 0u64);
+    for y in y.saturating_sub(radius)..=y.saturating_add(radius) {
+        for x in x.saturating_sub(radius)..=x.saturating_add(radius) {
+            let color = pixel(x, y)?;
+            r += u64::from(color.r);
+            g += u64::from(color.g);
+            b += u64::from(color.b);
+            count += 1;
+        }
+    }
+    Ok(Color::rgb(
+        (r / count) as u8,
+        (g / count) as u8,
+        (b / count) as u8,
+    ))
+}
+
+/// Used when no screen is available, every read fails.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoScreen;
+
+impl Screen for NoScreen {
+    fn pixel(&self, _x: i32, _y: i32) -> Result<Color, AppError> {
+        Err(AppError::Screen(
+            "screen capture is not available".to_string(),
+        ))
+    }
+}
+
+/// In-memory screen, e.g. for tests. Clones share the same pixels, so the
+/// picture can change while a macro is looking at it.
+#[derive(Debug, Clone)]
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    pixels: Arc<Mutex<Vec<Color>>>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32, color: Color) -> Self {
+        Self {
+            width,
+            height,
+            pixels: Arc::new(Mutex::new(vec![color; (width * height) as usize])),
+        }
+    }
+
+    pub fn set(&self, x: u32, y: u32, color: Color) {
+        self.fill(x, y, 1, 1, color);
+    }
+
+    /// Paints the rectangle with its top left corner at `x`, `y`
+    pub fn fill(&self, x: u32, y: u32, width: u32, height: u32, color: Color) {
+        let mut pixels = self.pixels.lock().unwrap();
+        for y in y..(y + height).min(self.height) {
+            for x in x..(x + width).min(self.width) {
+                pixels[(y * self.width + x) as usize] = color;
+            }
+        }
+    }
+}
+
+impl Screen for Framebuffer {
+    fn pixel(&self, x: i32, y: i32) -> Result<Color, AppError> {
+        if x < 0 || y < 0 || x as u32 >= self.width || y as u32 >= self.height {
+            return Err(AppError::Screen(format!(
+                "{}, {} is outside of the screen",
+                x, y
+            )));
+        }
+        Ok(self.pixels.lock().unwrap()[(y as u32 * self.width + x as u32) as usize])
+    }
+}
+
+/// The screen of the computer the macro runs on.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemScreen;
+
+#[cfg(target_os = "windows")]
+impl Screen for SystemScreen {
+    fn pixel(&self, x: i32, y: i32) -> Result<Color, AppError> {
+        win::ScreenDc::get()?.pixel(x, y)
+    }
+
+    /// Reads the whole square through a single device context
+    fn average(&self, x: i32, y: i32, radius: u32) -> Result<Color, AppError> {
+        let dc = win::ScreenDc::get()?;
+        average_of(x, y, radius, |x, y| dc.pixel(x, y))
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod win {
+    use std::ffi::c_void;
+
+    use super::Color;
+    use crate::errors::AppError;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetDC(hwnd: *mut c_void) -> *mut c_void;
+        fn ReleaseDC(hwnd: *mut c_void, hdc: *mut c_void) -> i32;
+    }
+    #[link(name = "gdi32")]
+    extern "system" {
+        fn GetPixel(hdc: *mut c_void, x: i32, y: i32) -> u32;
+    }
+    const CLR_INVALID: u32 = 0xFFFF_FFFF;
+
+    /// Device context of the whole screen, released when dropped
+    pub struct ScreenDc(*mut c_void);
+
+    impl ScreenDc {
+        pub fn get() -> Result<Self, AppError> {
+            // SAFETY: a null window asks for the screen, the context is
+            // released in `drop`
+            let hdc = unsafe { GetDC(std::ptr::null_mut()) };
+            if hdc.is_null() {
+                return Err(AppError::Screen("could not access the screen".to_string()));
+            }
+            Ok(Self(hdc))
+        }
+
+        pub fn pixel(&self, x: i32, y: i32) -> Result<Color, AppError> {
+            // SAFETY: the context is valid until `self` is dropped,
+            // GetPixel only reads from it
+            let colorref = unsafe { GetPixel(self.0, x, y) };
+            if colorref == CLR_INVALID {
+                return Err(AppError::Screen(format!(
+                    "could not read the pixel at {}, {}",
+                    x, y
+                )));
+            }
+            // COLORREF is 0x00BBGGRR
+            Ok(Color::rgb(
+                (colorref & 0xFF) as u8,
+                ((colorref >> 8) & 0xFF) as u8,
+                ((colorref >> 16) & 0xFF) as u8,
+            ))
+        }
+    }
+
+    impl Drop for ScreenDc {
+        fn drop(&mut self) {
+            // SAFETY: the context came from GetDC for the screen
+            unsafe {
+                ReleaseDC(std::ptr::null_mut(), self.0);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+impl Screen for SystemScreen {
+    fn pixel(&self, _x: i32, _y: i32) -> Result<Color, AppError> {
+        Err(AppError::Screen(
+            "screen capture is only supported on Windows".to_string(),
+        ))
+    }
+}
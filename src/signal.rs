@@ -0,0 +1,144 @@
+use std::{
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::actions::LoopFrame;
+
+#[derive(Debug, Default)]
+struct State {
+    stopped: bool,
+    paused: bool,
+    step: Option<Step>,
+}
+
+/// How far a paused macro runs before it pauses again
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// Pause before the next action, also inside a nested loop
+    Into,
+    /// Pause before the next action that is not nested deeper
+    /// than the one the macro was paused at
+    Over,
+}
+
+/// Lets the GUI stop, pause, resume and step through a running macro.
+/// Clones share the same state, and every thread waiting on the signal
+/// wakes up as soon as it changes.
+#[derive(Debug, Clone, Default)]
+pub struct ControlSignal {
+    inner: Arc<(Mutex<State>, Condvar)>,
+}
+
+impl ControlSignal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn stop(&self) {
+        self.update(|state| state.stopped = true);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.state().stopped
+    }
+
+    pub fn pause(&self) {
+        self.update(|state| {
+            state.paused = true;
+            state.step = None;
+        });
+    }
+
+    pub fn resume(&self) {
+        self.update(|state| {
+            state.paused = false;
+            state.step = None;
+        });
+    }
+
+    pub fn toggle_pause(&self) {
+        if self.is_paused() {
+            self.resume();
+        } else {
+            self.pause();
+        }
+    }
+
+    /// Resumes a paused macro until the executor reaches the end of `step`
+    pub fn step(&self, step: Step) {
+        self.update(|state| {
+            state.paused = false;
+            state.step = Some(step);
+        });
+    }
+
+    /// The step the macro is currently running, if any
+    pub fn current_step(&self) -> Option<Step> {
+        self.state().step
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state().paused
+    }
+
+    /// Blocks for `timeout` or until the macro is stopped or paused.
+    /// Returns whether it was interrupted.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let (state, condvar) = &*self.inner;
+        let deadline = Instant::now() + timeout;
+        let mut guard = state.lock().unwrap();
+        while !guard.stopped && !guard.paused {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            guard = condvar.wait_timeout(guard, deadline - now).unwrap().0;
+        }
+        guard.stopped || guard.paused
+    }
+
+    /// Blocks as long as the macro is paused and not stopped
+    pub fn wait_while_paused(&self) {
+        let (state, condvar) = &*self.inner;
+        let mut guard = state.lock().unwrap();
+        while guard.paused && !guard.stopped {
+            guard = condvar.wait(guard).unwrap();
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.inner.0.lock().unwrap()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut State)) {
+        let (state, condvar) = &*self.inner;
+        f(&mut state.lock().unwrap());
+        condvar.notify_all();
+    }
+}
+
+/// Where a running macro currently is, published by the executor for the GUI.
+/// Clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    frames: Arc<Mutex<Vec<LoopFrame>>>,
+}
+
+impl Progress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, frames: &[LoopFrame]) {
+        let mut guard = self.frames.lock().unwrap();
+        guard.clear();
+        guard.extend_from_slice(frames);
+    }
+
+    /// The loops enclosing the current action, outermost first.
+    /// Empty if nothing is running.
+    pub fn get(&self) -> Vec<LoopFrame> {
+        self.frames.lock().unwrap().clone()
+    }
+}
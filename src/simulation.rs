@@ -0,0 +1,99 @@
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::actions::{ExecutionContext, LoopAction};
+use crate::backend::{InputEvent, RecordingBackend};
+use crate::clock::{Clock, VirtualClock};
+use crate::errors::AppError;
+use crate::screen::{NoScreen, Screen};
+
+/// One input event of a simulated run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineEntry {
+    /// Simulated time since the start of the macro
+    pub at: Duration,
+    /// Label of the action that emitted the event, e.g. `1.3`
+    pub path: String,
+    pub event: InputEvent,
+    /// Cursor position after the event
+    pub location: (i32, i32),
+}
+
+#[derive(Debug, Clone)]
+pub struct SimulationOptions {
+    /// Cursor position when the macro starts
+    pub start_location: (i32, i32),
+    /// Wall time the macro starts at, time of day limits count from it
+    pub start_time: SystemTime,
+    /// Infinite loops are cut off after this much simulated time ...
+    pub max_duration: Duration,
+    /// ... or after this many events ...
+    pub max_events: usize,
+    /// ... or after this many actions, whichever comes first
+    pub max_actions: usize,
+    pub seed: Option<u64>,
+    /// File the macro was loaded from, includes are relative to it
+    pub file: Option<PathBuf>,
+}
+
+impl Default for SimulationOptions {
+    fn default() -> Self {
+        Self {
+            start_location: (0, 0),
+            start_time: SystemTime::now(),
+            max_duration: Duration::from_secs(60 * 60),
+            max_events: 10_000,
+            max_actions: 100_000,
+            seed: None,
+            file: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    pub timeline: Vec<TimelineEntry>,
+    /// Simulated run time of the whole macro
+    pub duration: Duration,
+    /// Whether the run was cut off by one of the limits in `SimulationOptions`
+    pub truncated: bool,
+}
+
+/// Runs `root` without touching the OS and returns everything it would do.
+/// Conditions on the screen fail, see [`simulate_on`].
+pub fn simulate(root: &LoopAction, options: SimulationOptions) -> Result<Simulation, AppError> {
+    simulate_on(root, options, &NoScreen)
+}
+
+/// Like [`simulate`], but conditions look at `screen`
+pub fn simulate_on(
+    root: &LoopAction,
+    options: SimulationOptions,
+    screen: &dyn Screen,
+) -> Result<Simulation, AppError> {
+    let clock = VirtualClock::new().starting_at(options.start_time);
+    let (x, y) = options.start_location;
+    let mut backend = RecordingBackend::new()
+        .with_location(x, y)
+        .with_clock(clock.clone());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock)
+        .with_screen(screen)
+        .with_timeline();
+    ctx.time_limit = Some(options.max_duration);
+    ctx.event_limit = Some(options.max_events);
+    ctx.action_limit = Some(options.max_actions);
+    if let Some(seed) = options.seed {
+        ctx = ctx.with_seed(seed);
+    }
+    if let Some(file) = &options.file {
+        ctx = ctx.with_file(file);
+    }
+
+    root.clone().execute(&mut ctx)?;
+
+    Ok(Simulation {
+        truncated: ctx.limit_reached(),
+        timeline: ctx.timeline.take().unwrap_or_default(),
+        duration: clock.now(),
+    })
+}
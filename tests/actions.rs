@@ -0,0 +1,539 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::{InputBackend, InputEvent, RecordingBackend};
+use auto_clicker::clock::{Clock, VirtualClock};
+use auto_clicker::errors::AppError;
+use common::*;
+use enigo::{Axis, Button, Coordinate, Direction, InputError, Key};
+use std::time::Duration;
+
+#[test]
+fn press_down_and_up_emits_press_then_release() {
+    let backend = run(
+        repeat(1, vec![press(Key::Shift.into(), true, true, 0)]),
+        RecordingBackend::new(),
+    );
+
+    assert_eq!(
+        backend.inputs(),
+        vec![
+            InputEvent::Key(Key::Shift, Direction::Press),
+            InputEvent::Key(Key::Shift, Direction::Release),
+        ]
+    );
+}
+
+#[test]
+fn press_only_down_or_only_up() {
+    let backend = run(
+        repeat(
+            1,
+            vec![
+                press(Button::Left.into(), true, false, 0),
+                press(Button::Left.into(), false, true, 0),
+            ],
+        ),
+        RecordingBackend::new(),
+    );
+
+    assert_eq!(
+        backend.inputs(),
+        vec![
+            InputEvent::Button(Button::Left, Direction::Press),
+            InputEvent::Button(Button::Left, Direction::Release),
+        ]
+    );
+}
+
+#[test]
+fn press_holds_for_hold_time() {
+    let backend = run(
+        repeat(1, vec![press(Button::Right.into(), true, true, 20)]),
+        RecordingBackend::new(),
+    );
+
+    let times: Vec<_> = backend.events().iter().map(|e| e.at).collect();
+    assert_eq!(times, vec![ms(0), ms(20)]);
+}
+
+#[test]
+fn delay_after_separates_actions() {
+    let tab = PressAction {
+        keybutton: Key::Tab.into(),
+        down: true,
+        up: true,
+        hold_time_ms: 5.into(),
+        delay_after_ms: 15.into(),
+    };
+    let backend = run(
+        repeat(1, vec![tab.clone().into(), tab.into()]),
+        RecordingBackend::new(),
+    );
+
+    let times: Vec<_> = backend.events().iter().map(|e| e.at).collect();
+    assert_eq!(times, vec![ms(0), ms(5), ms(20), ms(25)]);
+}
+
+#[test]
+fn nested_loops_repeat_in_order() {
+    let inner = repeat(2, vec![press(Key::Unicode('a').into(), true, true, 0)]);
+    let backend = run(
+        repeat(
+            2,
+            vec![press(Key::Return.into(), true, true, 0), inner.into()],
+        ),
+        RecordingBackend::new(),
+    );
+
+    let enter = [
+        InputEvent::Key(Key::Return, Direction::Press),
+        InputEvent::Key(Key::Return, Direction::Release),
+    ];
+    let a = [
+        InputEvent::Key(Key::Unicode('a'), Direction::Press),
+        InputEvent::Key(Key::Unicode('a'), Direction::Release),
+    ];
+    let once = [&enter[..], &a[..], &a[..]].concat();
+    assert_eq!(backend.inputs(), [&once[..], &once[..]].concat());
+}
+
+#[test]
+fn zero_iterations_emit_nothing() {
+    let backend = run(
+        repeat(0, vec![press(Key::Space.into(), true, true, 0)]),
+        RecordingBackend::new(),
+    );
+
+    assert!(backend.events().is_empty());
+}
+
+#[test]
+fn instant_move_is_a_single_event() {
+    let backend = run(
+        repeat(
+            2,
+            vec![move_to(5, -3, true, 0), move_to(100, 200, false, 0)],
+        ),
+        RecordingBackend::new().with_location(10, 10),
+    );
+
+    assert_eq!(
+        backend.inputs(),
+        vec![
+            InputEvent::MoveMouse(5, -3, Coordinate::Rel),
+            InputEvent::MoveMouse(100, 200, Coordinate::Abs),
+            InputEvent::MoveMouse(5, -3, Coordinate::Rel),
+            InputEvent::MoveMouse(100, 200, Coordinate::Abs),
+        ]
+    );
+    assert_eq!(backend.location().unwrap(), (100, 200));
+}
+
+#[test]
+fn timed_move_interpolates_in_relative_steps() {
+    let backend = run(
+        repeat(1, vec![move_to(120, 60, false, 10)]),
+        RecordingBackend::new().with_location(20, 10),
+    );
+
+    assert_eq!(
+        backend.inputs(),
+        vec![
+            InputEvent::MoveMouse(0, 0, Coordinate::Rel),
+            InputEvent::MoveMouse(30, 15, Coordinate::Rel),
+            InputEvent::MoveMouse(30, 15, Coordinate::Rel),
+            InputEvent::MoveMouse(30, 15, Coordinate::Rel),
+            InputEvent::MoveMouse(10, 5, Coordinate::Rel),
+        ]
+    );
+    let times: Vec<_> = backend.events().iter().map(|e| e.at).collect();
+    assert_eq!(times, vec![ms(0), ms(3), ms(6), ms(9), ms(10)]);
+    assert_eq!(backend.location().unwrap(), (120, 60));
+}
+
+#[test]
+fn timed_relative_move_ends_at_offset() {
+    let backend = run(
+        repeat(1, vec![move_to(-7, 13, true, 8)]),
+        RecordingBackend::new().with_location(50, 50),
+    );
+
+    assert!(backend
+        .inputs()
+        .iter()
+        .all(|e| matches!(e, InputEvent::MoveMouse(_, _, Coordinate::Rel))));
+    assert_eq!(backend.location().unwrap(), (43, 63));
+}
+
+#[test]
+fn mixed_tree_emits_exact_sequence() {
+    let drag = repeat(
+        1,
+        vec![
+            move_to(10, 10, false, 0),
+            press(Button::Left.into(), true, false, 0),
+            move_to(30, 0, true, 0),
+            press(Button::Left.into(), false, true, 0),
+        ],
+    );
+    let delay = DelayAction {
+        random: false,
+        delay_ms_min: 1.into(),
+        delay_ms_max: 1.into(),
+    };
+    let backend = run(
+        repeat(2, vec![drag.into(), delay.into()]),
+        RecordingBackend::new(),
+    );
+
+    let once = [
+        InputEvent::MoveMouse(10, 10, Coordinate::Abs),
+        InputEvent::Button(Button::Left, Direction::Press),
+        InputEvent::MoveMouse(30, 0, Coordinate::Rel),
+        InputEvent::Button(Button::Left, Direction::Release),
+    ];
+    assert_eq!(backend.inputs(), [&once[..], &once[..]].concat());
+    assert_eq!(backend.location().unwrap(), (40, 10));
+}
+
+#[test]
+fn long_delays_are_simulated_instantly() {
+    let ten_minutes = DelayAction {
+        random: false,
+        delay_ms_min: (10 * 60 * 1000).into(),
+        delay_ms_max: 0.into(),
+    };
+    let clock = VirtualClock::new();
+    let mut backend = RecordingBackend::new();
+    let mut ctx = ExecutionContext::new(&mut backend, &clock);
+    repeat(3, vec![ten_minutes.into()])
+        .execute(&mut ctx)
+        .unwrap();
+
+    assert_eq!(clock.now(), Duration::from_secs(30 * 60));
+}
+
+#[test]
+fn random_delay_is_reproducible_with_seed() {
+    let delay = DelayAction {
+        random: true,
+        delay_ms_min: 100.into(),
+        delay_ms_max: 200.into(),
+    };
+    let elapsed = |seed| {
+        let clock = VirtualClock::new();
+        let mut backend = RecordingBackend::new();
+        let mut ctx = ExecutionContext::new(&mut backend, &clock).with_seed(seed);
+        repeat(10, vec![delay.clone().into()])
+            .execute(&mut ctx)
+            .unwrap();
+        clock.now()
+    };
+
+    let first = elapsed(7);
+    assert_eq!(first, elapsed(7));
+    assert!(first >= ms(1000) && first < ms(2000));
+}
+
+fn text(text: &str, char_delay_ms: u64, jitter_ms: u64) -> Action {
+    TextAction {
+        text: text.to_string(),
+        char_delay_ms,
+        jitter_ms,
+        delay_after_ms: 0.into(),
+    }
+    .into()
+}
+
+#[test]
+fn text_types_every_character_with_char_delay() {
+    let backend = run(
+        repeat(1, vec![text("hé🙂", 10, 0)]),
+        RecordingBackend::new(),
+    );
+
+    assert_eq!(
+        backend.inputs(),
+        vec![
+            InputEvent::Text('h'),
+            InputEvent::Text('é'),
+            InputEvent::Text('🙂'),
+        ]
+    );
+    let times: Vec<_> = backend.events().iter().map(|e| e.at).collect();
+    assert_eq!(times, vec![ms(0), ms(10), ms(20)]);
+}
+
+#[test]
+fn text_jitter_stays_in_range() {
+    let backend = run(
+        repeat(1, vec![text("abcdefghij", 10, 5)]),
+        RecordingBackend::new(),
+    );
+
+    let times: Vec<_> = backend.events().iter().map(|e| e.at).collect();
+    assert!(times
+        .windows(2)
+        .all(|pair| pair[1] - pair[0] >= ms(10) && pair[1] - pair[0] <= ms(15)));
+}
+
+#[test]
+fn text_falls_back_to_unicode_key_clicks() {
+    let clock = VirtualClock::new();
+    let mut backend = NoMoveBackend(RecordingBackend::new());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock);
+    repeat(1, vec![text("ok", 0, 0)]).execute(&mut ctx).unwrap();
+
+    assert_eq!(
+        backend.0.inputs(),
+        vec![
+            InputEvent::Key(Key::Unicode('o'), Direction::Click),
+            InputEvent::Key(Key::Unicode('k'), Direction::Click),
+        ]
+    );
+}
+
+#[test]
+fn chord_releases_in_reverse_order_after_hold_time() {
+    let chord = ChordAction {
+        modifiers: vec![Key::Control.into(), Key::Shift.into()],
+        key: Key::Unicode('s').into(),
+        hold_time_ms: 30.into(),
+        delay_after_ms: 0.into(),
+    };
+    let backend = run(repeat(1, vec![chord.into()]), RecordingBackend::new());
+
+    assert_eq!(
+        backend.inputs(),
+        vec![
+            InputEvent::Key(Key::Control, Direction::Press),
+            InputEvent::Key(Key::Shift, Direction::Press),
+            InputEvent::Key(Key::Unicode('s'), Direction::Press),
+            InputEvent::Key(Key::Unicode('s'), Direction::Release),
+            InputEvent::Key(Key::Shift, Direction::Release),
+            InputEvent::Key(Key::Control, Direction::Release),
+        ]
+    );
+    let times: Vec<_> = backend.events().iter().map(|e| e.at).collect();
+    assert_eq!(times, vec![ms(0), ms(0), ms(0), ms(30), ms(30), ms(30)]);
+}
+
+#[test]
+fn chord_without_modifiers_is_a_single_press() {
+    let chord = ChordAction {
+        modifiers: Vec::new(),
+        key: Button::Left.into(),
+        hold_time_ms: 0.into(),
+        delay_after_ms: 0.into(),
+    };
+    let backend = run(repeat(1, vec![chord.into()]), RecordingBackend::new());
+
+    assert_eq!(
+        backend.inputs(),
+        vec![
+            InputEvent::Button(Button::Left, Direction::Press),
+            InputEvent::Button(Button::Left, Direction::Release),
+        ]
+    );
+}
+
+fn scroll(axis: Axis, amount: i32, scroll_time_ms: u64) -> Action {
+    ScrollAction {
+        axis,
+        amount,
+        scroll_time_ms,
+        delay_after_ms: 0.into(),
+    }
+    .into()
+}
+
+#[test]
+fn instant_scroll_is_a_single_event() {
+    let backend = run(
+        repeat(1, vec![scroll(Axis::Horizontal, -5, 0)]),
+        RecordingBackend::new(),
+    );
+
+    assert_eq!(
+        backend.inputs(),
+        vec![InputEvent::Scroll(-5, Axis::Horizontal)]
+    );
+}
+
+#[test]
+fn smooth_scroll_spreads_notches_over_scroll_time() {
+    let backend = run(
+        repeat(1, vec![scroll(Axis::Vertical, -3, 100)]),
+        RecordingBackend::new(),
+    );
+
+    assert_eq!(
+        backend.inputs(),
+        vec![InputEvent::Scroll(-1, Axis::Vertical); 3]
+    );
+    let times: Vec<_> = backend.events().iter().map(|e| e.at).collect();
+    assert_eq!(times, vec![ms(33), ms(66), ms(100)]);
+}
+
+fn click_at(x: i32, y: i32, clicks: u32, jitter_radius: u32) -> Action {
+    ClickAtAction {
+        x,
+        y,
+        button: Button::Left,
+        clicks,
+        click_interval_ms: 40,
+        jitter_radius,
+        delay_after_ms: 0.into(),
+    }
+    .into()
+}
+
+#[test]
+fn click_at_moves_then_clicks_with_interval() {
+    let backend = run(
+        repeat(1, vec![click_at(300, 200, 2, 0)]),
+        RecordingBackend::new(),
+    );
+
+    assert_eq!(
+        backend.inputs(),
+        vec![
+            InputEvent::MoveMouse(300, 200, Coordinate::Abs),
+            InputEvent::Button(Button::Left, Direction::Press),
+            InputEvent::Button(Button::Left, Direction::Release),
+            InputEvent::Button(Button::Left, Direction::Press),
+            InputEvent::Button(Button::Left, Direction::Release),
+        ]
+    );
+    let times: Vec<_> = backend.events().iter().map(|e| e.at).collect();
+    assert_eq!(times, vec![ms(0), ms(0), ms(0), ms(40), ms(40)]);
+}
+
+#[test]
+fn click_at_jitter_stays_within_radius() {
+    let backend = run(
+        repeat(200, vec![click_at(100, 100, 1, 5)]),
+        RecordingBackend::new(),
+    );
+
+    let positions: Vec<_> = backend
+        .inputs()
+        .into_iter()
+        .filter_map(|e| match e {
+            InputEvent::MoveMouse(x, y, _) => Some((x - 100, y - 100)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(positions.len(), 200);
+    assert!(positions.iter().all(|(dx, dy)| dx * dx + dy * dy <= 25));
+    assert!(positions.iter().any(|&offset| offset != (0, 0)));
+}
+
+#[test]
+fn click_at_with_a_huge_jitter_radius() {
+    for radius in [50_000, u32::MAX] {
+        let backend = run(
+            repeat(20, vec![click_at(100, 100, 1, radius)]),
+            RecordingBackend::new(),
+        );
+
+        let moves = backend
+            .inputs()
+            .into_iter()
+            .filter(|e| matches!(e, InputEvent::MoveMouse(..)))
+            .count();
+        assert_eq!(moves, 20, "radius {}", radius);
+    }
+}
+
+fn drag(duration_ms: u64, easing: Easing) -> DragAction {
+    DragAction {
+        start_x: 10.into(),
+        start_y: 20.into(),
+        end_x: 110.into(),
+        end_y: 70.into(),
+        button: Button::Left,
+        duration_ms: duration_ms.into(),
+        easing,
+        delay_after_ms: 0.into(),
+    }
+}
+
+#[test]
+fn drag_presses_moves_and_releases() {
+    let backend = run(
+        repeat(1, vec![drag(30, Easing::EaseInOut).into()]),
+        RecordingBackend::new(),
+    );
+
+    let inputs = backend.inputs();
+    assert_eq!(inputs[0], InputEvent::MoveMouse(10, 20, Coordinate::Abs));
+    assert_eq!(
+        inputs[1],
+        InputEvent::Button(Button::Left, Direction::Press)
+    );
+    assert_eq!(
+        inputs.last(),
+        Some(&InputEvent::Button(Button::Left, Direction::Release))
+    );
+    assert_eq!(backend.location().unwrap(), (110, 70));
+    assert_eq!(backend.events().last().unwrap().at, ms(30));
+}
+
+/// Records like `RecordingBackend` but fails every relative mouse move
+struct NoRelativeMoveBackend(RecordingBackend);
+
+impl InputBackend for NoRelativeMoveBackend {
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), AppError> {
+        self.0.key(key, direction)
+    }
+    fn button(&mut self, button: Button, direction: Direction) -> Result<(), AppError> {
+        self.0.button(button, direction)
+    }
+    fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> Result<(), AppError> {
+        match coordinate {
+            Coordinate::Abs => self.0.move_mouse(x, y, coordinate),
+            Coordinate::Rel => Err(InputError::Simulate("relative moves are unsupported").into()),
+        }
+    }
+    fn location(&self) -> Result<(i32, i32), AppError> {
+        self.0.location()
+    }
+    fn scroll(&mut self, length: i32, axis: Axis) -> Result<(), AppError> {
+        self.0.scroll(length, axis)
+    }
+}
+
+#[test]
+fn drag_releases_button_when_move_fails() {
+    let clock = VirtualClock::new();
+    let mut backend = NoRelativeMoveBackend(RecordingBackend::new());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock);
+
+    assert!(drag(30, Easing::Linear).execute(&mut ctx).is_err());
+    assert!(ctx.held().is_empty());
+    assert_eq!(
+        backend.0.inputs(),
+        vec![
+            InputEvent::MoveMouse(10, 20, Coordinate::Abs),
+            InputEvent::Button(Button::Left, Direction::Press),
+            InputEvent::Button(Button::Left, Direction::Release),
+        ]
+    );
+}
+
+#[test]
+fn easings_start_at_zero_and_end_at_one() {
+    for easing in [
+        Easing::Linear,
+        Easing::EaseIn,
+        Easing::EaseOut,
+        Easing::EaseInOut,
+    ] {
+        assert_eq!(easing.apply(0.0), 0.0);
+        assert_eq!(easing.apply(1.0), 1.0);
+        let steps: Vec<f64> = (0..=10).map(|i| easing.apply(i as f64 / 10.0)).collect();
+        assert!(steps.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+}
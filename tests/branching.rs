@@ -0,0 +1,140 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::RecordingBackend;
+use auto_clicker::clock::VirtualClock;
+use auto_clicker::errors::AppError;
+use auto_clicker::expr::Value;
+use common::*;
+use enigo::Key;
+
+#[test]
+fn comparisons() {
+    assert_eq!(eval("n == 3").unwrap(), Value::Bool(true));
+    assert_eq!(eval("n != 3").unwrap(), Value::Bool(false));
+    assert_eq!(eval("n < 3").unwrap(), Value::Bool(false));
+    assert_eq!(eval("n <= 3").unwrap(), Value::Bool(true));
+    assert_eq!(eval("n * 2 > 5").unwrap(), Value::Bool(true));
+    assert_eq!(eval("n >= 4").unwrap(), Value::Bool(false));
+    assert_eq!(eval(r#"mode == "fast""#).unwrap(), Value::Bool(true));
+    assert_eq!(eval(r#""abc" < "abd""#).unwrap(), Value::Bool(true));
+}
+
+#[test]
+fn boolean_logic_binds_weaker_than_comparisons() {
+    assert_eq!(eval("n > 1 && n < 5").unwrap(), Value::Bool(true));
+    assert_eq!(
+        eval("n == 1 || n == 3 && false").unwrap(),
+        Value::Bool(false)
+    );
+    assert_eq!(
+        eval("(n == 1 || n == 3) && true").unwrap(),
+        Value::Bool(true)
+    );
+    assert_eq!(eval("!(n == 3) || !false").unwrap(), Value::Bool(true));
+}
+
+#[test]
+fn logic_skips_the_right_side_when_it_can() {
+    assert_eq!(eval("false && unknown").unwrap(), Value::Bool(false));
+    assert_eq!(eval("true || 1 / 0 == 0").unwrap(), Value::Bool(true));
+}
+
+#[test]
+fn mismatched_types_are_errors() {
+    for source in ["n < mode", "n && true", "!n", "true + 1"] {
+        assert!(
+            matches!(eval(source), Err(AppError::Expression(_))),
+            "{:?}",
+            source
+        );
+    }
+}
+
+#[test]
+fn every_third_iteration() {
+    let root = repeat(
+        7,
+        vec![if_else(
+            expression("iteration % 3 == 0"),
+            vec![tap(Key::F5)],
+            vec![tap(Key::Space)],
+        )],
+    );
+
+    let backend = run(root, RecordingBackend::new());
+
+    assert_eq!(
+        pressed(&backend),
+        vec![
+            Key::F5,
+            Key::Space,
+            Key::Space,
+            Key::F5,
+            Key::Space,
+            Key::Space,
+            Key::F5
+        ]
+    );
+}
+
+#[test]
+fn nested_branches_see_the_loop_iteration() {
+    let root = repeat(
+        4,
+        vec![if_else(
+            expression("i % 2 == 0"),
+            vec![if_else(
+                expression("i > 0"),
+                vec![tap(Key::F5)],
+                vec![tap(Key::Tab)],
+            )],
+            vec![],
+        )],
+    );
+
+    let backend = run(root, RecordingBackend::new());
+
+    assert_eq!(pressed(&backend), vec![Key::Tab, Key::F5]);
+}
+
+#[test]
+fn condition_must_be_true_or_false() {
+    let root = repeat(1, vec![if_else(expression("i + 1"), vec![], vec![])]);
+    let clock = VirtualClock::new();
+    let mut backend = RecordingBackend::new();
+    let mut ctx = ExecutionContext::new(&mut backend, &clock);
+
+    let err = root.execute(&mut ctx).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "Action 1.0 failed: Expression error: 1 is not true or false"
+    );
+}
+
+#[test]
+fn wait_until_a_variable_reaches_a_value() {
+    let clicks: Action = IncrementAction {
+        name: "clicks".to_string(),
+        by: 1,
+    }
+    .into();
+    let mut wait = WaitUntilAction::new(expression("clicks >= 3"));
+    wait.timeout_ms = 0;
+    let root = repeat(
+        1,
+        vec![
+            repeat(3, vec![clicks, tap(Key::Space)]).into(),
+            wait.into(),
+            tap(Key::Tab),
+        ],
+    );
+
+    let backend = run(root, RecordingBackend::new());
+
+    assert_eq!(
+        pressed(&backend),
+        vec![Key::Space, Key::Space, Key::Space, Key::Tab]
+    );
+}
@@ -0,0 +1,135 @@
+#![allow(dead_code)]
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::{InputBackend, InputEvent, RecordingBackend};
+use auto_clicker::clock::VirtualClock;
+use auto_clicker::errors::AppError;
+use auto_clicker::expr::{Expr, Value};
+use enigo::{Axis, Button, Coordinate, Direction, InputError, Key};
+use std::time::Duration;
+
+/// Records like `RecordingBackend` but fails every mouse move
+pub struct NoMoveBackend(pub RecordingBackend);
+
+impl InputBackend for NoMoveBackend {
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), AppError> {
+        self.0.key(key, direction)
+    }
+    fn button(&mut self, button: Button, direction: Direction) -> Result<(), AppError> {
+        self.0.button(button, direction)
+    }
+    fn move_mouse(&mut self, _x: i32, _y: i32, _coordinate: Coordinate) -> Result<(), AppError> {
+        Err(InputError::Simulate("mouse is unplugged").into())
+    }
+    fn location(&self) -> Result<(i32, i32), AppError> {
+        self.0.location()
+    }
+    fn scroll(&mut self, length: i32, axis: Axis) -> Result<(), AppError> {
+        self.0.scroll(length, axis)
+    }
+}
+
+pub fn press(keybutton: KeyButton, down: bool, up: bool, hold_time_ms: u64) -> Action {
+    PressAction {
+        keybutton,
+        down,
+        up,
+        hold_time_ms: hold_time_ms.into(),
+        delay_after_ms: 0.into(),
+    }
+    .into()
+}
+
+/// Presses and releases `key` right away
+pub fn tap(key: Key) -> Action {
+    press(key.into(), true, true, 0)
+}
+
+pub fn move_to(x: i32, y: i32, relative: bool, move_time_ms: u64) -> Action {
+    MoveAction {
+        x: x.into(),
+        y: y.into(),
+        relative,
+        move_time_ms: move_time_ms.into(),
+        delay_after_ms: 0.into(),
+        path: MovePath::default(),
+    }
+    .into()
+}
+
+pub fn if_else(
+    condition: Condition,
+    then_actions: Vec<Action>,
+    else_actions: Vec<Action>,
+) -> Action {
+    let mut ifaction = IfAction::new(condition);
+    ifaction.then_branch.actions = then_actions;
+    ifaction.else_branch.actions = else_actions;
+    ifaction.into()
+}
+
+pub fn named(name: &str, mut loopaction: LoopAction) -> LoopAction {
+    loopaction.name = name.to_string();
+    loopaction
+}
+
+pub fn expression(source: &str) -> Condition {
+    Condition::Expression(source.to_string())
+}
+
+pub fn repeat(iterations: u64, actions: Vec<Action>) -> LoopAction {
+    LoopAction {
+        name: String::new(),
+        infinite: false,
+        iterations: iterations.into(),
+        actions,
+        breakpoints: Vec::new(),
+        time_limit: None,
+        procedures: Vec::new(),
+    }
+}
+
+pub fn frame(loop_index: u32, action: usize, iteration: u64) -> LoopFrame {
+    LoopFrame {
+        loop_index,
+        action,
+        iteration,
+    }
+}
+
+/// Executes `root` on a virtual clock shared with the backend
+pub fn run(root: LoopAction, backend: RecordingBackend) -> RecordingBackend {
+    let clock = VirtualClock::new();
+    let mut backend = backend.with_clock(clock.clone());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock);
+    root.execute(&mut ctx).unwrap();
+    backend
+}
+
+/// Keys in the order they were pressed
+pub fn pressed(backend: &RecordingBackend) -> Vec<Key> {
+    backend
+        .inputs()
+        .into_iter()
+        .filter_map(|event| match event {
+            InputEvent::Key(key, Direction::Press) => Some(key),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Evaluates `source` with the variables `n = 3`, `x = 7`,
+/// `mode = "fast"` and `name = "bob"`
+pub fn eval(source: &str) -> Result<Value, AppError> {
+    Expr::parse(source)?.eval(&|name| match name {
+        "n" => Some(Value::Int(3)),
+        "x" => Some(Value::Int(7)),
+        "mode" => Some("fast".into()),
+        "name" => Some("bob".into()),
+        _ => None,
+    })
+}
+
+pub fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
@@ -0,0 +1,239 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::RecordingBackend;
+use auto_clicker::clock::VirtualClock;
+use auto_clicker::errors::AppError;
+use auto_clicker::screen::{Color, Framebuffer, Screen};
+use common::*;
+use enigo::Key;
+use std::cell::Cell;
+
+const BLACK: Color = Color::rgb(0, 0, 0);
+const RED: Color = Color::rgb(250, 10, 10);
+
+fn pixel_is(x: i32, y: i32, color: Color, tolerance: u8) -> Condition {
+    Condition::PixelColor {
+        x,
+        y,
+        radius: 0,
+        color,
+        tolerance,
+    }
+}
+
+fn run_on(root: LoopAction, screen: &dyn Screen) -> Result<RecordingBackend, AppError> {
+    let clock = VirtualClock::new();
+    let mut backend = RecordingBackend::new().with_clock(clock.clone());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock).with_screen(screen);
+    root.execute(&mut ctx)?;
+    Ok(backend)
+}
+
+#[test]
+fn runs_the_branch_matching_the_pixel() {
+    let screen = Framebuffer::new(10, 10, BLACK);
+    screen.set(3, 4, RED);
+    let root = repeat(
+        1,
+        vec![
+            if_else(
+                pixel_is(3, 4, RED, 0),
+                vec![tap(Key::Space)],
+                vec![tap(Key::Tab)],
+            ),
+            if_else(
+                pixel_is(4, 4, RED, 0),
+                vec![tap(Key::Space)],
+                vec![tap(Key::Tab)],
+            ),
+        ],
+    );
+
+    let backend = run_on(root, &screen).unwrap();
+
+    assert_eq!(pressed(&backend), vec![Key::Space, Key::Tab]);
+}
+
+#[test]
+fn tolerance_applies_to_every_channel() {
+    let screen = Framebuffer::new(1, 1, Color::rgb(100, 100, 100));
+
+    for (tolerance, matches) in [(4, false), (5, true)] {
+        let root = repeat(
+            1,
+            vec![if_else(
+                pixel_is(0, 0, Color::rgb(105, 95, 100), tolerance),
+                vec![tap(Key::Space)],
+                vec![tap(Key::Tab)],
+            )],
+        );
+        let backend = run_on(root, &screen).unwrap();
+        let expected = if matches { Key::Space } else { Key::Tab };
+        assert_eq!(pressed(&backend), vec![expected], "tolerance {}", tolerance);
+    }
+}
+
+#[test]
+fn radius_compares_the_average_of_the_region() {
+    let screen = Framebuffer::new(10, 10, BLACK);
+    // a white dot in the middle of a 3x3 black square averages to dark gray
+    screen.set(5, 5, Color::rgb(255, 255, 255));
+    let condition = Condition::PixelColor {
+        x: 5,
+        y: 5,
+        radius: 1,
+        color: Color::rgb(28, 28, 28),
+        tolerance: 1,
+    };
+    let root = repeat(
+        1,
+        vec![if_else(
+            condition,
+            vec![tap(Key::Space)],
+            vec![tap(Key::Tab)],
+        )],
+    );
+
+    let backend = run_on(root, &screen).unwrap();
+
+    assert_eq!(pressed(&backend), vec![Key::Space]);
+}
+
+#[test]
+fn radius_is_limited() {
+    let screen = Framebuffer::new(10, 10, BLACK);
+    for radius in [21, 1 << 31, u32::MAX] {
+        let condition = Condition::PixelColor {
+            x: 5,
+            y: 5,
+            radius,
+            color: BLACK,
+            tolerance: 0,
+        };
+        let root = repeat(1, vec![if_else(condition, vec![], vec![])]);
+
+        let Err(err) = run_on(root, &screen) else {
+            panic!("radius {} succeeded", radius);
+        };
+
+        assert!(matches!(
+            &err,
+            AppError::Action { source, .. } if matches!(**source, AppError::Screen(_))
+        ));
+    }
+}
+
+/// Turns red and black again on every read
+struct BlinkingScreen(Cell<bool>);
+
+impl Screen for BlinkingScreen {
+    fn pixel(&self, _x: i32, _y: i32) -> Result<Color, AppError> {
+        self.0.set(!self.0.get());
+        Ok(if self.0.get() { RED } else { BLACK })
+    }
+}
+
+#[test]
+fn condition_is_checked_every_time_the_action_runs() {
+    let screen = BlinkingScreen(Cell::new(false));
+    let root = repeat(
+        3,
+        vec![if_else(
+            pixel_is(0, 0, RED, 0),
+            vec![tap(Key::Space)],
+            vec![tap(Key::Tab)],
+        )],
+    );
+
+    let backend = run_on(root, &screen).unwrap();
+
+    assert_eq!(pressed(&backend), vec![Key::Space, Key::Tab, Key::Space]);
+}
+
+#[test]
+fn branches_are_numbered_like_nested_loops() {
+    let screen = Framebuffer::new(1, 1, BLACK);
+    let root = repeat(
+        1,
+        vec![
+            repeat(1, vec![]).into(),
+            if_else(
+                pixel_is(0, 0, RED, 0),
+                vec![repeat(1, vec![]).into()],
+                vec![tap(Key::Tab), move_to(1, 1, false, 0)],
+            ),
+            tap(Key::Space),
+        ],
+    );
+    let clock = VirtualClock::new();
+    let mut backend = RecordingBackend::new().with_clock(clock.clone());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock)
+        .with_screen(&screen)
+        .with_timeline();
+
+    root.execute(&mut ctx).unwrap();
+
+    // loop 2 is the first action, 3 the then branch, 4 the loop in it, 5 the else branch
+    let paths: Vec<String> = ctx
+        .timeline
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.path)
+        .collect();
+    assert_eq!(paths, vec!["5.0", "5.0", "5.1", "1.2", "1.2"]);
+}
+
+#[test]
+fn failing_screen_reports_the_action() {
+    let screen = Framebuffer::new(1, 1, BLACK);
+    let root = repeat(1, vec![if_else(pixel_is(5, 5, RED, 0), vec![], vec![])]);
+
+    let Err(err) = run_on(root, &screen) else {
+        panic!("reading outside of the screen succeeded");
+    };
+
+    assert!(matches!(
+        &err,
+        AppError::Action { path, source } if path == "1.0" && matches!(**source, AppError::Screen(_))
+    ));
+}
+
+#[test]
+fn without_a_screen_conditions_fail() {
+    let root = repeat(1, vec![if_else(pixel_is(0, 0, RED, 0), vec![], vec![])]);
+    let clock = VirtualClock::new();
+    let mut backend = RecordingBackend::new().with_clock(clock.clone());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock);
+
+    assert!(root.execute(&mut ctx).is_err());
+}
+
+#[test]
+fn cursor_regions_reaching_past_the_coordinate_range() {
+    let cursor_in = |x: i32, y: i32, width: u32, height: u32| Condition::CursorIn {
+        x,
+        y,
+        width,
+        height,
+    };
+    let root = repeat(
+        1,
+        vec![
+            if_else(
+                cursor_in(0, 0, u32::MAX, u32::MAX),
+                vec![tap(Key::Space)],
+                vec![tap(Key::Tab)],
+            ),
+            if_else(
+                cursor_in(i32::MAX - 1, i32::MAX - 1, 10, 10),
+                vec![tap(Key::Space)],
+                vec![tap(Key::Tab)],
+            ),
+        ],
+    );
+
+    let backend = run(root, RecordingBackend::new().with_location(100, 100));
+
+    assert_eq!(pressed(&backend), vec![Key::Space, Key::Tab]);
+}
@@ -0,0 +1,190 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::{InputEvent, RecordingBackend};
+use auto_clicker::clock::{Clock, RealClock, VirtualClock};
+use auto_clicker::signal::ControlSignal;
+use common::*;
+use enigo::{Button, Direction, Key};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Runs `root` on the real clock and stops it after `stop_after`.
+/// Returns the backend and how long the macro kept running after the stop.
+fn run_and_stop(root: LoopAction, stop_after: Duration) -> (RecordingBackend, Duration) {
+    let stop = ControlSignal::new();
+    let stop_clone = stop.clone();
+    let handle = thread::spawn(move || {
+        let clock = RealClock::new();
+        let mut backend = RecordingBackend::new();
+        let mut ctx = ExecutionContext::new(&mut backend, &clock).with_control(stop_clone);
+        root.execute(&mut ctx).unwrap();
+        (backend, Instant::now())
+    });
+    thread::sleep(stop_after);
+    let stopped_at = Instant::now();
+    stop.stop();
+    let (backend, finished_at) = handle.join().unwrap();
+    (backend, finished_at - stopped_at)
+}
+
+#[test]
+fn stop_interrupts_long_delay() {
+    let minute = DelayAction {
+        random: false,
+        delay_ms_min: 60_000.into(),
+        delay_ms_max: 0.into(),
+    };
+    let (_, lag) = run_and_stop(repeat(1, vec![minute.into()]), ms(20));
+
+    assert!(lag < ms(500), "stop took {:?}", lag);
+}
+
+#[test]
+fn stop_interrupts_hold_and_still_releases() {
+    let (backend, lag) = run_and_stop(
+        repeat(1, vec![press(Button::Left.into(), true, true, 60_000)]),
+        ms(20),
+    );
+
+    assert!(lag < ms(500), "stop took {:?}", lag);
+    assert_eq!(
+        backend.inputs(),
+        vec![
+            InputEvent::Button(Button::Left, Direction::Press),
+            InputEvent::Button(Button::Left, Direction::Release),
+        ]
+    );
+}
+
+#[test]
+fn stop_interrupts_delay_after() {
+    let press_then_wait = PressAction {
+        keybutton: Button::Left.into(),
+        down: true,
+        up: true,
+        hold_time_ms: 0.into(),
+        delay_after_ms: 60_000.into(),
+    };
+    let (backend, lag) = run_and_stop(repeat(3, vec![press_then_wait.into()]), ms(20));
+
+    assert!(lag < ms(500), "stop took {:?}", lag);
+    assert_eq!(backend.inputs().len(), 2);
+}
+
+#[test]
+fn stop_interrupts_slow_move_without_jumping_to_target() {
+    let (backend, lag) = run_and_stop(repeat(1, vec![move_to(10_000, 0, true, 60_000)]), ms(50));
+
+    assert!(lag < ms(500), "stop took {:?}", lag);
+    let x: i32 = backend
+        .inputs()
+        .iter()
+        .map(|e| match e {
+            InputEvent::MoveMouse(x, _, _) => *x,
+            _ => 0,
+        })
+        .sum();
+    assert!(x < 100, "moved {} px", x);
+}
+
+#[test]
+fn stopped_virtual_clock_does_not_advance() {
+    let clock = VirtualClock::new();
+    let stop = ControlSignal::new();
+    stop.stop();
+
+    assert!(clock.sleep_interruptible(Duration::from_secs(10), &stop));
+    assert_eq!(clock.now(), Duration::ZERO);
+}
+
+#[test]
+fn signal_wait_times_out_when_not_stopped() {
+    let stop = ControlSignal::new();
+    let start = Instant::now();
+
+    assert!(!stop.wait_timeout(ms(10)));
+    assert!(start.elapsed() >= ms(10));
+}
+
+/// Starts `root` on the real clock in a background thread
+fn spawn(root: LoopAction, control: ControlSignal) -> thread::JoinHandle<RecordingBackend> {
+    thread::spawn(move || {
+        let clock = RealClock::new();
+        let mut backend = RecordingBackend::new().with_clock(clock);
+        let mut ctx = ExecutionContext::new(&mut backend, &clock).with_control(control);
+        root.execute(&mut ctx).unwrap();
+        backend
+    })
+}
+
+#[test]
+fn pause_freezes_delay_and_resume_continues_it() {
+    let control = ControlSignal::new();
+    let root = repeat(
+        1,
+        vec![
+            press(Key::Unicode('a').into(), true, true, 0),
+            DelayAction {
+                random: false,
+                delay_ms_min: 100.into(),
+                delay_ms_max: 0.into(),
+            }
+            .into(),
+            press(Key::Unicode('b').into(), true, true, 0),
+        ],
+    );
+    let handle = spawn(root, control.clone());
+
+    thread::sleep(ms(30));
+    control.pause();
+    thread::sleep(ms(200));
+    assert!(!handle.is_finished());
+    control.resume();
+    let backend = handle.join().unwrap();
+
+    let events = backend.events();
+    assert_eq!(events.len(), 4);
+    // the 100 ms delay plus roughly 200 ms of pause
+    assert!(events[2].at - events[1].at >= ms(250));
+}
+
+#[test]
+fn pause_keeps_loop_position() {
+    let control = ControlSignal::new();
+    let click = PressAction {
+        keybutton: Button::Left.into(),
+        down: true,
+        up: true,
+        hold_time_ms: 0.into(),
+        delay_after_ms: 20.into(),
+    };
+    let handle = spawn(repeat(10, vec![click.into()]), control.clone());
+
+    thread::sleep(ms(50));
+    control.pause();
+    thread::sleep(ms(100));
+    assert!(!handle.is_finished());
+    control.resume();
+    let backend = handle.join().unwrap();
+
+    // every iteration ran exactly once, nothing was skipped or repeated
+    assert_eq!(backend.inputs().len(), 20);
+}
+
+#[test]
+fn stop_while_paused_ends_macro() {
+    let control = ControlSignal::new();
+    let mut root = repeat(1, vec![press(Key::Space.into(), true, true, 10)]);
+    root.infinite = true;
+    let handle = spawn(root, control.clone());
+
+    thread::sleep(ms(30));
+    control.pause();
+    thread::sleep(ms(30));
+    let stopped_at = Instant::now();
+    control.stop();
+    handle.join().unwrap();
+
+    assert!(stopped_at.elapsed() < ms(500));
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::{InputEvent, RecordingBackend};
+use auto_clicker::clock::VirtualClock;
+use auto_clicker::signal::{ControlSignal, Progress, Step};
+use common::*;
+use enigo::{Direction, Key};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Runs `root` on another thread, returns the controls and the recorded inputs on join
+fn spawn(root: LoopAction) -> (ControlSignal, Progress, JoinHandle<Vec<InputEvent>>) {
+    let control = ControlSignal::new();
+    let progress = Progress::new();
+    let handle = {
+        let control = control.clone();
+        let progress = progress.clone();
+        thread::spawn(move || {
+            let clock = VirtualClock::new();
+            let mut backend = RecordingBackend::new();
+            let mut ctx = ExecutionContext::new(&mut backend, &clock)
+                .with_control(control)
+                .with_progress(progress);
+            root.execute(&mut ctx).unwrap();
+            backend.inputs()
+        })
+    };
+    (control, progress, handle)
+}
+
+/// Waits until the executor paused and returns where
+fn paused_at(control: &ControlSignal, progress: &Progress) -> Vec<LoopFrame> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !control.is_paused() {
+        assert!(Instant::now() < deadline, "macro did not pause");
+        thread::sleep(Duration::from_millis(1));
+    }
+    progress.get()
+}
+
+/// `1.0` is a loop over `2.0` that runs twice, followed by `1.1`
+fn nested() -> LoopAction {
+    let mut root = repeat(
+        1,
+        vec![
+            repeat(2, vec![tap(Key::Unicode('a'))]).into(),
+            tap(Key::Unicode('b')),
+        ],
+    );
+    root.toggle_breakpoint(0);
+    root
+}
+
+#[test]
+fn breakpoint_pauses_before_the_action() {
+    let mut root = repeat(2, vec![tap(Key::Unicode('a')), tap(Key::Unicode('b'))]);
+    root.toggle_breakpoint(1);
+    let (control, progress, handle) = spawn(root);
+
+    assert_eq!(paused_at(&control, &progress), vec![frame(1, 1, 0)]);
+    control.resume();
+    assert_eq!(paused_at(&control, &progress), vec![frame(1, 1, 1)]);
+    control.resume();
+
+    assert_eq!(handle.join().unwrap().len(), 8);
+}
+
+#[test]
+fn step_into_enters_nested_loop() {
+    let (control, progress, handle) = spawn(nested());
+
+    assert_eq!(paused_at(&control, &progress), vec![frame(1, 0, 0)]);
+    control.step(Step::Into);
+    assert_eq!(
+        paused_at(&control, &progress),
+        vec![frame(1, 0, 0), frame(2, 0, 0)]
+    );
+    control.step(Step::Into);
+    assert_eq!(
+        paused_at(&control, &progress),
+        vec![frame(1, 0, 0), frame(2, 0, 1)]
+    );
+    control.step(Step::Into);
+    assert_eq!(paused_at(&control, &progress), vec![frame(1, 1, 0)]);
+    control.resume();
+
+    assert_eq!(handle.join().unwrap().len(), 6);
+}
+
+#[test]
+fn step_over_runs_nested_loop_at_once() {
+    let (control, progress, handle) = spawn(nested());
+
+    assert_eq!(paused_at(&control, &progress), vec![frame(1, 0, 0)]);
+    control.step(Step::Over);
+    assert_eq!(paused_at(&control, &progress), vec![frame(1, 1, 0)]);
+    control.step(Step::Over);
+
+    let inputs = handle.join().unwrap();
+    assert_eq!(
+        inputs.last(),
+        Some(&InputEvent::Key(Key::Unicode('b'), Direction::Release))
+    );
+}
+
+#[test]
+fn stopping_at_breakpoint_skips_the_action() {
+    let mut root = repeat(1, vec![tap(Key::Unicode('a')), tap(Key::Unicode('b'))]);
+    root.toggle_breakpoint(1);
+    let (control, progress, handle) = spawn(root);
+
+    paused_at(&control, &progress);
+    control.stop();
+
+    assert_eq!(handle.join().unwrap().len(), 2);
+}
+
+#[test]
+fn removing_an_action_moves_later_breakpoints() {
+    let mut root = repeat(
+        1,
+        vec![
+            tap(Key::Unicode('a')),
+            tap(Key::Unicode('b')),
+            tap(Key::Unicode('c')),
+            tap(Key::Unicode('d')),
+        ],
+    );
+    root.toggle_breakpoint(1);
+    root.toggle_breakpoint(3);
+    root.remove_action(1);
+
+    assert_eq!(root.breakpoints, vec![2]);
+}
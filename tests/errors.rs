@@ -0,0 +1,70 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::RecordingBackend;
+use auto_clicker::clock::VirtualClock;
+use auto_clicker::errors::AppError;
+use common::*;
+use enigo::Key;
+
+fn run_failing(root: LoopAction) -> AppError {
+    let clock = VirtualClock::new();
+    let mut backend = NoMoveBackend(RecordingBackend::new());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock);
+    root.execute(&mut ctx).unwrap_err()
+}
+
+#[test]
+fn execution_error_names_the_failing_action() {
+    let err = run_failing(repeat(
+        1,
+        vec![
+            press(Key::Tab.into(), true, true, 0),
+            move_to(10, 10, false, 0),
+        ],
+    ));
+
+    assert!(matches!(
+        &err,
+        AppError::Action { path, source } if path == "1.1" && matches!(**source, AppError::Input(_))
+    ));
+    assert!(err
+        .to_string()
+        .starts_with("Action 1.1 failed: Input error: "));
+}
+
+#[test]
+fn execution_error_names_the_innermost_action() {
+    let inner = repeat(
+        1,
+        vec![
+            press(Key::Tab.into(), true, true, 0),
+            move_to(10, 10, false, 0),
+        ],
+    );
+    let err = run_failing(repeat(
+        1,
+        vec![press(Key::Tab.into(), true, true, 0), inner.into()],
+    ));
+
+    assert!(matches!(err, AppError::Action { path, .. } if path == "2.1"));
+}
+
+#[test]
+fn loading_an_invalid_file_is_a_deserialize_error() {
+    let path = std::env::temp_dir().join(format!("invalid-{}.aclick", std::process::id()));
+    std::fs::write(&path, b"{ not a macro").unwrap();
+    let mut root = repeat(1, vec![]);
+    let result = root.load_from_disk(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(result, Err(AppError::Deserialize(_))));
+}
+
+#[test]
+fn loading_a_missing_file_is_an_io_error() {
+    let mut root = repeat(1, vec![]);
+    let result = root.load_from_disk(&"this/file/does/not/exist.aclick");
+
+    assert!(matches!(result, Err(AppError::Io(_))));
+}
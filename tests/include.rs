@@ -0,0 +1,201 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::RecordingBackend;
+use auto_clicker::clock::VirtualClock;
+use auto_clicker::errors::AppError;
+use auto_clicker::simulation::{simulate, SimulationOptions};
+use common::*;
+use enigo::Key;
+use std::path::{Path, PathBuf};
+
+/// A fresh directory for the files of one test
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("include-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn save(path: &Path, root: &LoopAction) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    root.save_to_disk(&path).unwrap();
+}
+
+fn include(path: &str) -> Action {
+    IncludeAction {
+        path: path.to_string(),
+    }
+    .into()
+}
+
+/// Runs `root` as if it was loaded from `file`
+fn run_from(file: &Path, root: LoopAction) -> Result<RecordingBackend, AppError> {
+    let clock = VirtualClock::new();
+    let mut backend = RecordingBackend::new().with_clock(clock.clone());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock).with_file(file);
+    root.execute(&mut ctx)?;
+    Ok(backend)
+}
+
+#[test]
+fn runs_the_included_root_loop_inline() {
+    let dir = test_dir("inline");
+    save(&dir.join("login.aclick"), &repeat(2, vec![tap(Key::Tab)]));
+    let root = repeat(
+        2,
+        vec![tap(Key::Space), include("login.aclick"), tap(Key::Return)],
+    );
+
+    let backend = run_from(&dir.join("main.aclick"), root).unwrap();
+
+    let once = [Key::Space, Key::Tab, Key::Tab, Key::Return];
+    assert_eq!(pressed(&backend), [once, once].concat());
+}
+
+#[test]
+fn paths_are_relative_to_the_including_file() {
+    let dir = test_dir("relative");
+    save(
+        &dir.join("shared/steps.aclick"),
+        &repeat(1, vec![include("../common/last.aclick")]),
+    );
+    save(
+        &dir.join("common/last.aclick"),
+        &repeat(1, vec![tap(Key::Escape)]),
+    );
+    let root = repeat(1, vec![include("shared/steps.aclick")]);
+
+    let backend = run_from(&dir.join("main.aclick"), root).unwrap();
+
+    assert_eq!(pressed(&backend), vec![Key::Escape]);
+}
+
+#[test]
+fn included_files_see_the_variables() {
+    let dir = test_dir("variables");
+    let set: Action = SetVariableAction {
+        name: "n".to_string(),
+        value: "n * 2".to_string(),
+    }
+    .into();
+    save(&dir.join("double.aclick"), &repeat(1, vec![set]));
+    let start: Action = SetVariableAction {
+        name: "n".to_string(),
+        value: "3".to_string(),
+    }
+    .into();
+    let root = repeat(1, vec![start, include("double.aclick")]);
+    let clock = VirtualClock::new();
+    let mut backend = RecordingBackend::new().with_clock(clock.clone());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock).with_file(dir.join("main.aclick"));
+
+    root.execute(&mut ctx).unwrap();
+
+    assert_eq!(ctx.variables["n"], 6.into());
+}
+
+#[test]
+fn missing_file_is_reported_with_its_path() {
+    let dir = test_dir("missing");
+    let root = repeat(1, vec![tap(Key::Space), include("nope.aclick")]);
+
+    let Err(err) = run_from(&dir.join("main.aclick"), root) else {
+        panic!("including a missing file succeeded");
+    };
+
+    let AppError::Action { path, source } = &err else {
+        panic!("unexpected {:?}", err);
+    };
+    assert_eq!(path, "1.1");
+    assert!(matches!(
+        &**source,
+        AppError::Include { path, source } if path == "nope.aclick" && matches!(**source, AppError::Io(_))
+    ));
+}
+
+#[test]
+fn malformed_file_is_a_deserialize_error() {
+    let dir = test_dir("malformed");
+    std::fs::write(dir.join("broken.aclick"), b"{ not a macro").unwrap();
+    let root = repeat(1, vec![include("broken.aclick")]);
+
+    let Err(err) = run_from(&dir.join("main.aclick"), root) else {
+        panic!("including a malformed file succeeded");
+    };
+
+    assert!(err
+        .to_string()
+        .starts_with("Action 1.0 failed: In `broken.aclick`: Could not read macro"));
+}
+
+#[test]
+fn errors_inside_are_labeled_within_the_included_file() {
+    let dir = test_dir("inner-error");
+    save(
+        &dir.join("fails.aclick"),
+        &repeat(1, vec![tap(Key::Tab), include("nope.aclick")]),
+    );
+    let root = repeat(1, vec![include("fails.aclick")]);
+
+    let Err(err) = run_from(&dir.join("main.aclick"), root) else {
+        panic!("the included file did not fail");
+    };
+
+    assert!(err
+        .to_string()
+        .starts_with("Action 1.0 failed: In `fails.aclick`: Action 1.1 failed: In `nope.aclick`"));
+}
+
+#[test]
+fn including_itself_is_an_error() {
+    let dir = test_dir("self");
+    let main = repeat(1, vec![tap(Key::Space), include("main.aclick")]);
+    save(&dir.join("main.aclick"), &main);
+
+    let Err(err) = run_from(&dir.join("main.aclick"), main) else {
+        panic!("a file including itself succeeded");
+    };
+
+    assert!(err.to_string().ends_with("includes itself"));
+}
+
+#[test]
+fn cycles_over_several_files_are_errors() {
+    let dir = test_dir("cycle");
+    save(&dir.join("a.aclick"), &repeat(1, vec![include("b.aclick")]));
+    save(&dir.join("b.aclick"), &repeat(1, vec![include("a.aclick")]));
+    let root = repeat(1, vec![include("a.aclick")]);
+
+    let Err(err) = run_from(&dir.join("main.aclick"), root) else {
+        panic!("a cycle of includes succeeded");
+    };
+
+    assert!(err.to_string().ends_with("includes itself"));
+}
+
+#[test]
+fn the_same_file_may_be_included_twice() {
+    let dir = test_dir("twice");
+    save(&dir.join("tab.aclick"), &repeat(1, vec![tap(Key::Tab)]));
+    let root = repeat(1, vec![include("tab.aclick"), include("tab.aclick")]);
+
+    let backend = run_from(&dir.join("main.aclick"), root).unwrap();
+
+    assert_eq!(pressed(&backend), vec![Key::Tab, Key::Tab]);
+}
+
+#[test]
+fn simulation_resolves_includes_next_to_the_file() {
+    let dir = test_dir("simulation");
+    save(&dir.join("tab.aclick"), &repeat(3, vec![tap(Key::Tab)]));
+    let root = repeat(1, vec![include("tab.aclick")]);
+    let options = SimulationOptions {
+        file: Some(dir.join("main.aclick")),
+        ..Default::default()
+    };
+
+    let sim = simulate(&root, options).unwrap();
+
+    assert_eq!(sim.timeline.len(), 6);
+}
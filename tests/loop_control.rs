@@ -0,0 +1,156 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::RecordingBackend;
+use auto_clicker::clock::VirtualClock;
+use auto_clicker::errors::AppError;
+use common::*;
+use enigo::Key;
+
+fn jump(target: &str, condition: Option<&str>) -> LoopJump {
+    LoopJump {
+        target: target.to_string(),
+        condition: condition.map(expression),
+    }
+}
+
+#[test]
+fn break_leaves_the_innermost_loop() {
+    let root = repeat(
+        2,
+        vec![
+            repeat(3, vec![tap(Key::Space), Action::Break(jump("", None))]).into(),
+            tap(Key::Tab),
+        ],
+    );
+
+    let backend = run(root, RecordingBackend::new());
+
+    assert_eq!(
+        pressed(&backend),
+        vec![Key::Space, Key::Tab, Key::Space, Key::Tab]
+    );
+}
+
+#[test]
+fn break_leaves_a_named_outer_loop() {
+    let root = repeat(
+        1,
+        vec![
+            named(
+                "outer",
+                repeat(
+                    3,
+                    vec![
+                        repeat(3, vec![tap(Key::Space), Action::Break(jump("outer", None))]).into(),
+                        tap(Key::Tab),
+                    ],
+                ),
+            )
+            .into(),
+            tap(Key::Escape),
+        ],
+    );
+
+    let backend = run(root, RecordingBackend::new());
+
+    assert_eq!(pressed(&backend), vec![Key::Space, Key::Escape]);
+}
+
+#[test]
+fn continue_skips_the_rest_of_the_iteration() {
+    let root = repeat(
+        3,
+        vec![
+            tap(Key::Space),
+            Action::Continue(jump("", Some("i == 1"))),
+            tap(Key::Tab),
+        ],
+    );
+
+    let backend = run(root, RecordingBackend::new());
+
+    assert_eq!(
+        pressed(&backend),
+        vec![Key::Space, Key::Tab, Key::Space, Key::Space, Key::Tab]
+    );
+}
+
+#[test]
+fn continue_of_a_named_loop_ends_the_inner_one() {
+    let root = named(
+        "outer",
+        repeat(
+            2,
+            vec![
+                repeat(
+                    3,
+                    vec![tap(Key::Space), Action::Continue(jump("outer", None))],
+                )
+                .into(),
+                tap(Key::Tab),
+            ],
+        ),
+    );
+
+    let backend = run(root, RecordingBackend::new());
+
+    assert_eq!(pressed(&backend), vec![Key::Space, Key::Space]);
+}
+
+#[test]
+fn guarded_break_waits_for_its_condition() {
+    let root = repeat(
+        1,
+        vec![repeat(
+            10,
+            vec![tap(Key::Space), Action::Break(jump("", Some("i == 2")))],
+        )
+        .into()],
+    );
+
+    let backend = run(root, RecordingBackend::new());
+
+    assert_eq!(pressed(&backend).len(), 3);
+}
+
+#[test]
+fn break_in_a_branch_leaves_the_loop_around_it() {
+    let stop = if_else(
+        expression("i == 1"),
+        vec![Action::Break(jump("", None)), tap(Key::Escape)],
+        vec![],
+    );
+    let root = repeat(1, vec![repeat(5, vec![stop, tap(Key::Space)]).into()]);
+
+    let backend = run(root, RecordingBackend::new());
+
+    assert_eq!(pressed(&backend), vec![Key::Space]);
+}
+
+#[test]
+fn unknown_loop_name_is_an_error() {
+    let root = repeat(1, vec![tap(Key::Space), Action::Break(jump("nope", None))]);
+    let clock = VirtualClock::new();
+    let mut backend = RecordingBackend::new().with_clock(clock.clone());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock);
+
+    let err = root.execute(&mut ctx).unwrap_err();
+
+    assert!(matches!(
+        &err,
+        AppError::Action { path, source } if path == "1.1" && matches!(**source, AppError::NoSuchLoop(_))
+    ));
+    assert_eq!(pressed(&backend), vec![Key::Space]);
+}
+
+#[test]
+fn jumps_are_saved_without_defaults() {
+    let json = serde_json::to_string(&Action::Break(jump("", None))).unwrap();
+    assert_eq!(json, r#"{"Break":{}}"#);
+
+    let loaded: LoopAction =
+        serde_json::from_str(r#"{"infinite":false,"iterations":2,"actions":[],"breakpoints":[]}"#)
+            .unwrap();
+    assert!(loaded.name.is_empty());
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::simulation::{simulate, SimulationOptions};
+use common::*;
+
+fn shaped_move(x: i32, y: i32, shape: PathShape, jitter_px: u32) -> Action {
+    MoveAction {
+        x: x.into(),
+        y: y.into(),
+        relative: false,
+        move_time_ms: 300.into(),
+        delay_after_ms: 0.into(),
+        path: MovePath { shape, jitter_px },
+    }
+    .into()
+}
+
+/// Cursor positions during the move, starting at (0, 0)
+fn positions(action: Action, seed: u64) -> Vec<(i32, i32)> {
+    let options = SimulationOptions {
+        seed: Some(seed),
+        ..Default::default()
+    };
+    simulate(&repeat(1, vec![action]), options)
+        .unwrap()
+        .timeline
+        .iter()
+        .map(|entry| entry.location)
+        .collect()
+}
+
+const ALL_SHAPES: [PathShape; 4] = [
+    PathShape::Linear,
+    PathShape::EaseInOut,
+    PathShape::Bezier,
+    PathShape::Overshoot,
+];
+
+#[test]
+fn every_shape_ends_exactly_at_the_target() {
+    for shape in ALL_SHAPES {
+        for jitter_px in [0, 3] {
+            let path = positions(shaped_move(400, -250, shape, jitter_px), 1);
+            assert_eq!(path.last(), Some(&(400, -250)), "{:?}", shape);
+        }
+    }
+}
+
+#[test]
+fn overshoot_goes_past_the_target() {
+    let path = positions(shaped_move(400, 0, PathShape::Overshoot, 0), 1);
+
+    assert!(path.iter().any(|&(x, _)| x > 400));
+}
+
+#[test]
+fn bezier_leaves_the_straight_line() {
+    let path = positions(shaped_move(400, 0, PathShape::Bezier, 0), 1);
+
+    assert!(path.iter().any(|&(_, y)| y != 0));
+    assert_eq!(
+        path,
+        positions(shaped_move(400, 0, PathShape::Bezier, 0), 1)
+    );
+}
+
+#[test]
+fn ease_in_out_is_slower_at_the_ends() {
+    let path = positions(shaped_move(400, 0, PathShape::EaseInOut, 0), 1);
+    let step = |i: usize| path[i + 1].0 - path[i].0;
+
+    assert!(step(0) < step(path.len() / 2));
+    assert!(step(path.len() - 2) < step(path.len() / 2));
+}
+
+#[test]
+fn jitter_stays_close_to_the_path() {
+    let straight = positions(shaped_move(400, 0, PathShape::Linear, 0), 1);
+    let jittered = positions(shaped_move(400, 0, PathShape::Linear, 2), 1);
+
+    assert_eq!(straight.len(), jittered.len());
+    assert_ne!(straight, jittered);
+    assert!(straight
+        .iter()
+        .zip(&jittered)
+        .all(|(a, b)| (a.0 - b.0).abs() <= 2 && (a.1 - b.1).abs() <= 2));
+}
+
+#[test]
+fn files_without_a_path_move_in_a_straight_line() {
+    let json = r#"{"x":5,"y":6,"relative":false,"move_time_ms":10,"delay_after_ms":1}"#;
+    let action: MoveAction = serde_json::from_str(json).unwrap();
+
+    assert_eq!(action.path, MovePath::default());
+    assert_eq!(action.path.shape, PathShape::Linear);
+}
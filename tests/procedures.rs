@@ -0,0 +1,259 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::{InputEvent, RecordingBackend};
+use auto_clicker::clock::VirtualClock;
+use auto_clicker::errors::AppError;
+use auto_clicker::expr::{Param, Value};
+use common::*;
+use enigo::{Coordinate, Key};
+
+fn call(name: &str, args: &[&str]) -> Action {
+    CallAction {
+        name: name.to_string(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+    }
+    .into()
+}
+
+fn procedure(name: &str, params: &[&str], actions: Vec<Action>) -> Procedure {
+    Procedure {
+        name: name.to_string(),
+        params: params.iter().map(|param| param.to_string()).collect(),
+        body: LoopAction::once(actions),
+    }
+}
+
+fn click_at_expr(x: &str, y: &str) -> Action {
+    MoveAction {
+        x: Param::Expr(x.to_string()),
+        y: Param::Expr(y.to_string()),
+        relative: false,
+        move_time_ms: 0.into(),
+        delay_after_ms: 0.into(),
+        path: MovePath::default(),
+    }
+    .into()
+}
+
+fn try_run(root: LoopAction) -> Result<RecordingBackend, AppError> {
+    let clock = VirtualClock::new();
+    let mut backend = RecordingBackend::new().with_clock(clock.clone());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock);
+    root.execute(&mut ctx)?;
+    Ok(backend)
+}
+
+#[test]
+fn calls_run_the_body_every_time() {
+    let mut root = repeat(
+        2,
+        vec![call("login", &[]), tap(Key::Space), call("login", &[])],
+    );
+    root.procedures = vec![procedure(
+        "login",
+        &[],
+        vec![tap(Key::Tab), tap(Key::Return)],
+    )];
+
+    let backend = run(root, RecordingBackend::new());
+
+    let login = [Key::Tab, Key::Return];
+    let once: Vec<Key> = [&login[..], &[Key::Space], &login[..]].concat();
+    assert_eq!(pressed(&backend), [once.clone(), once].concat());
+}
+
+#[test]
+fn arguments_are_set_as_parameters() {
+    let mut root = repeat(2, vec![call("click", &["100 + i * 10", "50"])]);
+    root.procedures = vec![procedure(
+        "click",
+        &["x", "y"],
+        vec![click_at_expr("x", "y")],
+    )];
+
+    let backend = run(root, RecordingBackend::new());
+
+    assert_eq!(
+        backend.inputs(),
+        vec![
+            InputEvent::MoveMouse(100, 50, Coordinate::Abs),
+            InputEvent::MoveMouse(110, 50, Coordinate::Abs),
+        ]
+    );
+}
+
+#[test]
+fn parameters_shadow_variables_only_during_the_call() {
+    let set_x: Action = SetVariableAction {
+        name: "x".to_string(),
+        value: "7".to_string(),
+    }
+    .into();
+    let mut root = repeat(
+        1,
+        vec![set_x, call("show", &["x + 1"]), click_at_expr("x", "0")],
+    );
+    root.procedures = vec![procedure("show", &["x"], vec![click_at_expr("x", "0")])];
+
+    let clock = VirtualClock::new();
+    let mut backend = RecordingBackend::new().with_clock(clock.clone());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock);
+    root.execute(&mut ctx).unwrap();
+    let variables = ctx.variables.clone();
+
+    assert_eq!(
+        backend.inputs(),
+        vec![
+            InputEvent::MoveMouse(8, 0, Coordinate::Abs),
+            InputEvent::MoveMouse(7, 0, Coordinate::Abs),
+        ]
+    );
+    assert_eq!(variables["x"], Value::Int(7));
+}
+
+#[test]
+fn procedures_can_call_each_other() {
+    let mut root = repeat(1, vec![call("outer", &[])]);
+    root.procedures = vec![
+        procedure("inner", &[], vec![tap(Key::Tab)]),
+        procedure("outer", &[], vec![call("inner", &[]), tap(Key::Space)]),
+    ];
+
+    let backend = run(root, RecordingBackend::new());
+
+    assert_eq!(pressed(&backend), vec![Key::Tab, Key::Space]);
+}
+
+#[test]
+fn unknown_procedure_fails_the_action() {
+    let root = repeat(1, vec![tap(Key::Space), call("missing", &[])]);
+
+    let Err(err) = try_run(root) else {
+        panic!("calling a missing procedure succeeded");
+    };
+
+    assert_eq!(
+        err.to_string(),
+        "Action 1.1 failed: Procedure error: unknown procedure `missing`"
+    );
+}
+
+#[test]
+fn argument_count_must_match() {
+    let mut root = repeat(1, vec![call("click", &["1"])]);
+    root.procedures = vec![procedure("click", &["x", "y"], vec![])];
+
+    let Err(err) = try_run(root) else {
+        panic!("call with a missing argument succeeded");
+    };
+
+    assert_eq!(
+        err.to_string(),
+        "Action 1.0 failed: Procedure error: `click` takes 2 arguments but got 1"
+    );
+}
+
+#[test]
+fn endless_recursion_is_an_error() {
+    let mut root = repeat(1, vec![call("again", &[])]);
+    root.procedures = vec![procedure("again", &[], vec![call("again", &[])])];
+
+    let Err(err) = try_run(root) else {
+        panic!("endless recursion succeeded");
+    };
+
+    assert!(err.to_string().contains("nested deeper than"));
+}
+
+#[test]
+fn break_ends_the_call_but_not_the_caller() {
+    let mut root = named("outer", repeat(2, vec![call("stop", &[]), tap(Key::Tab)]));
+    root.procedures = vec![procedure(
+        "stop",
+        &[],
+        vec![
+            tap(Key::Space),
+            Action::Break(LoopJump::default()),
+            tap(Key::Escape),
+        ],
+    )];
+
+    let backend = run(root, RecordingBackend::new());
+
+    assert_eq!(
+        pressed(&backend),
+        vec![Key::Space, Key::Tab, Key::Space, Key::Tab]
+    );
+}
+
+#[test]
+fn caller_loops_are_out_of_reach() {
+    let mut root = named("outer", repeat(2, vec![call("leave", &[])]));
+    root.procedures = vec![procedure(
+        "leave",
+        &[],
+        vec![Action::Break(LoopJump {
+            target: "outer".to_string(),
+            condition: None,
+        })],
+    )];
+
+    let Err(err) = try_run(root) else {
+        panic!("break out of a procedure succeeded");
+    };
+
+    assert!(matches!(
+        &err,
+        AppError::Action { source, .. } if matches!(**source, AppError::NoSuchLoop(_))
+    ));
+}
+
+#[test]
+fn bodies_are_numbered_after_the_actions() {
+    let mut root = repeat(
+        1,
+        vec![
+            repeat(1, vec![]).into(),
+            call("login", &[]),
+            tap(Key::Space),
+        ],
+    );
+    root.procedures = vec![
+        procedure("unused", &[], vec![repeat(1, vec![]).into()]),
+        procedure("login", &[], vec![tap(Key::Tab)]),
+    ];
+    let clock = VirtualClock::new();
+    let mut backend = RecordingBackend::new().with_clock(clock.clone());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock).with_timeline();
+
+    root.execute(&mut ctx).unwrap();
+
+    // loop 2 is the first action, 3 the body of `unused` and 4 the loop in it
+    let paths: Vec<String> = ctx
+        .timeline
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.path)
+        .collect();
+    assert_eq!(paths, vec!["5.0", "5.0", "1.2", "1.2"]);
+}
+
+#[test]
+fn procedures_are_saved_with_the_macro() {
+    let mut root = repeat(1, vec![call("click", &["1", "2"])]);
+    root.procedures = vec![procedure(
+        "click",
+        &["x", "y"],
+        vec![click_at_expr("x", "y")],
+    )];
+
+    let json = serde_json::to_string(&root).unwrap();
+    let loaded: LoopAction = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(loaded.procedures.len(), 1);
+    assert_eq!(loaded.procedures[0].params, vec!["x", "y"]);
+    assert!(!serde_json::to_string(&repeat(1, vec![]))
+        .unwrap()
+        .contains("procedures"));
+}
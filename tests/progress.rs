@@ -0,0 +1,105 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::{InputBackend, RecordingBackend};
+use auto_clicker::clock::VirtualClock;
+use auto_clicker::errors::AppError;
+use auto_clicker::signal::Progress;
+use common::*;
+use enigo::{Axis, Button, Coordinate, Direction, Key};
+
+/// Records like `RecordingBackend` and takes a snapshot of the published
+/// progress on every key event
+struct SnapshotBackend {
+    inner: RecordingBackend,
+    progress: Progress,
+    snapshots: Vec<Vec<LoopFrame>>,
+}
+
+impl InputBackend for SnapshotBackend {
+    fn key(&mut self, key: Key, direction: Direction) -> Result<(), AppError> {
+        self.snapshots.push(self.progress.get());
+        self.inner.key(key, direction)
+    }
+    fn button(&mut self, button: Button, direction: Direction) -> Result<(), AppError> {
+        self.inner.button(button, direction)
+    }
+    fn move_mouse(&mut self, x: i32, y: i32, coordinate: Coordinate) -> Result<(), AppError> {
+        self.inner.move_mouse(x, y, coordinate)
+    }
+    fn location(&self) -> Result<(i32, i32), AppError> {
+        self.inner.location()
+    }
+    fn scroll(&mut self, length: i32, axis: Axis) -> Result<(), AppError> {
+        self.inner.scroll(length, axis)
+    }
+}
+
+fn snapshots(root: LoopAction) -> (Vec<Vec<LoopFrame>>, Progress) {
+    let progress = Progress::new();
+    let clock = VirtualClock::new();
+    let mut backend = SnapshotBackend {
+        inner: RecordingBackend::new(),
+        progress: progress.clone(),
+        snapshots: Vec::new(),
+    };
+    let mut ctx = ExecutionContext::new(&mut backend, &clock).with_progress(progress.clone());
+    root.execute(&mut ctx).unwrap();
+    (backend.snapshots, progress)
+}
+
+#[test]
+fn progress_follows_action_and_iteration() {
+    let (snapshots, _) = snapshots(repeat(
+        2,
+        vec![
+            press(Key::Tab.into(), true, false, 0),
+            press(Key::Tab.into(), false, true, 0),
+        ],
+    ));
+
+    assert_eq!(
+        snapshots,
+        vec![
+            vec![frame(1, 0, 0)],
+            vec![frame(1, 1, 0)],
+            vec![frame(1, 0, 1)],
+            vec![frame(1, 1, 1)],
+        ]
+    );
+}
+
+#[test]
+fn progress_contains_every_enclosing_loop() {
+    let inner = repeat(3, vec![press(Key::Space.into(), true, true, 0)]);
+    let (snapshots, _) = snapshots(repeat(
+        1,
+        vec![
+            repeat(1, vec![]).into(),
+            press(Key::Tab.into(), true, true, 0),
+            inner.into(),
+        ],
+    ));
+
+    assert_eq!(snapshots[0], vec![frame(1, 1, 0)]);
+    assert_eq!(snapshots[2], vec![frame(1, 2, 0), frame(3, 0, 0)]);
+    assert_eq!(snapshots[6], vec![frame(1, 2, 0), frame(3, 0, 2)]);
+}
+
+#[test]
+fn progress_is_cleared_when_macro_ends() {
+    let (_, progress) = snapshots(repeat(1, vec![press(Key::Tab.into(), true, true, 0)]));
+
+    assert!(progress.get().is_empty());
+}
+
+#[test]
+fn infinite_loop_counts_iterations() {
+    let mut inner = repeat(0, vec![press(Key::Tab.into(), true, true, 1000)]);
+    inner.infinite = true;
+    inner.time_limit = Some(TimeLimit::For { secs: 3 });
+    let (snapshots, _) = snapshots(repeat(1, vec![inner.into()]));
+
+    let iterations: Vec<u64> = snapshots.iter().map(|frames| frames[1].iteration).collect();
+    assert_eq!(iterations, vec![0, 0, 1, 1, 2, 2]);
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::RecordingBackend;
+use auto_clicker::expr::Param;
+use auto_clicker::ranged::{Ranged, Variation};
+use common::*;
+use enigo::Key;
+
+#[test]
+fn fixed_value_is_serialized_as_plain_number() {
+    let fixed: Ranged<u64> = 25.into();
+
+    assert_eq!(serde_json::to_string(&fixed).unwrap(), "25");
+    assert_eq!(serde_json::from_str::<Ranged<u64>>("25").unwrap(), fixed);
+}
+
+#[test]
+fn varying_value_survives_a_round_trip() {
+    let mut jitter = Ranged::jitter(-40, 5);
+    jitter.gaussian = true;
+    let json = serde_json::to_string(&jitter).unwrap();
+
+    assert_eq!(serde_json::from_str::<Ranged<i32>>(&json).unwrap(), jitter);
+}
+
+#[test]
+fn old_files_still_load() {
+    let json = r#"{"keybutton":{"KeyboardKey":"Tab"},"down":true,"up":true,"hold_time_ms":5,"delay_after_ms":1}"#;
+    let action: PressAction = serde_json::from_str(json).unwrap();
+
+    assert_eq!(action.hold_time_ms, Ranged::fixed(5).into());
+    assert!(matches!(
+        action.delay_after_ms,
+        Param::Ranged(Ranged {
+            variation: Variation::Fixed,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn samples_stay_within_bounds() {
+    let mut rng = fastrand::Rng::with_seed(3);
+    for gaussian in [false, true] {
+        let mut jitter = Ranged::jitter(100u64, 20);
+        jitter.gaussian = gaussian;
+        let mut range = Ranged::range(-10, 10);
+        range.gaussian = gaussian;
+
+        let jittered: Vec<u64> = (0..1000).map(|_| jitter.sample(&mut rng)).collect();
+        let ranged: Vec<i32> = (0..1000).map(|_| range.sample(&mut rng)).collect();
+
+        assert!(jittered.iter().all(|v| (80..=120).contains(v)));
+        assert!(ranged.iter().all(|v| (-10..=10).contains(v)));
+        assert!(jittered.iter().any(|&v| v != 100));
+    }
+}
+
+#[test]
+fn gaussian_prefers_the_middle() {
+    let mut rng = fastrand::Rng::with_seed(3);
+    let mut gaussian = Ranged::range(0u64, 600);
+    gaussian.gaussian = true;
+
+    let samples: Vec<u64> = (0..1000).map(|_| gaussian.sample(&mut rng)).collect();
+    let middle = samples.iter().filter(|v| (200..400).contains(*v)).count();
+    assert!(middle > 600);
+}
+
+#[test]
+fn jitter_never_goes_below_zero() {
+    assert_eq!(Ranged::jitter(5u64, 20).bounds(), (0, 25));
+}
+
+#[test]
+fn hold_time_varies_between_runs() {
+    let tab = PressAction {
+        keybutton: Key::Tab.into(),
+        down: true,
+        up: true,
+        hold_time_ms: Ranged::range(10, 50).into(),
+        delay_after_ms: 0.into(),
+    };
+    let backend = run(repeat(20, vec![tab.into()]), RecordingBackend::new());
+
+    let holds: Vec<_> = backend
+        .events()
+        .chunks(2)
+        .map(|pair| pair[1].at - pair[0].at)
+        .collect();
+    assert!(holds.iter().all(|hold| *hold >= ms(10) && *hold <= ms(50)));
+    assert!(holds.iter().any(|hold| *hold != holds[0]));
+}
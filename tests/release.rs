@@ -0,0 +1,93 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::{InputEvent, RecordingBackend};
+use auto_clicker::clock::VirtualClock;
+use auto_clicker::signal::ControlSignal;
+use common::*;
+use enigo::{Button, Direction, Key};
+
+#[test]
+fn held_keys_are_released_when_macro_ends() {
+    let backend = run(
+        repeat(
+            1,
+            vec![
+                press(Key::Shift.into(), true, false, 0),
+                press(Button::Left.into(), true, false, 0),
+            ],
+        ),
+        RecordingBackend::new(),
+    );
+
+    assert_eq!(
+        backend.inputs(),
+        vec![
+            InputEvent::Key(Key::Shift, Direction::Press),
+            InputEvent::Button(Button::Left, Direction::Press),
+            InputEvent::Button(Button::Left, Direction::Release),
+            InputEvent::Key(Key::Shift, Direction::Release),
+        ]
+    );
+}
+
+#[test]
+fn released_keys_are_not_released_twice() {
+    let backend = run(
+        repeat(
+            2,
+            vec![
+                press(Key::Control.into(), true, false, 0),
+                press(Key::Control.into(), false, true, 0),
+            ],
+        ),
+        RecordingBackend::new(),
+    );
+
+    assert_eq!(backend.inputs().len(), 4);
+}
+
+#[test]
+fn held_keys_are_released_when_stopped() {
+    let clock = VirtualClock::new();
+    let stop = ControlSignal::new();
+    stop.stop();
+    let mut backend = RecordingBackend::new();
+    let mut ctx = ExecutionContext::new(&mut backend, &clock).with_control(stop);
+    let mut root = repeat(1, vec![press(Key::Shift.into(), true, false, 0)]);
+    root.infinite = true;
+    root.execute(&mut ctx).unwrap();
+
+    assert_eq!(
+        backend.inputs(),
+        vec![
+            InputEvent::Key(Key::Shift, Direction::Press),
+            InputEvent::Key(Key::Shift, Direction::Release),
+        ]
+    );
+}
+
+#[test]
+fn held_keys_are_released_on_error() {
+    let clock = VirtualClock::new();
+    let mut backend = NoMoveBackend(RecordingBackend::new());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock);
+    let root = repeat(
+        1,
+        vec![
+            press(Button::Left.into(), true, false, 0),
+            move_to(100, 100, false, 0),
+            press(Button::Left.into(), false, true, 0),
+        ],
+    );
+
+    assert!(root.execute(&mut ctx).is_err());
+    assert!(ctx.held().is_empty());
+    assert_eq!(
+        backend.0.inputs(),
+        vec![
+            InputEvent::Button(Button::Left, Direction::Press),
+            InputEvent::Button(Button::Left, Direction::Release),
+        ]
+    );
+}
@@ -0,0 +1,170 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::InputEvent;
+use auto_clicker::simulation::{simulate, SimulationOptions};
+use common::*;
+use enigo::{Button, Coordinate, Direction, Key};
+use std::time::{Duration, SystemTime};
+
+#[test]
+fn timeline_labels_actions_like_the_editor() {
+    // 1.0 press, 1.1 loop #2 (2.0 move), 1.2 loop #3 (3.0 loop #4 (4.0 press)), 1.3 press
+    let innermost = repeat(1, vec![press(Key::Tab.into(), true, true, 0)]);
+    let root = repeat(
+        1,
+        vec![
+            press(Key::Space.into(), true, true, 0),
+            repeat(2, vec![move_to(1, 0, true, 0)]).into(),
+            repeat(1, vec![innermost.into()]).into(),
+            press(Key::Return.into(), true, true, 0),
+        ],
+    );
+
+    let sim = simulate(&root, SimulationOptions::default()).unwrap();
+    let paths: Vec<_> = sim.timeline.iter().map(|e| e.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        vec!["1.0", "1.0", "2.0", "2.0", "4.0", "4.0", "1.3", "1.3"]
+    );
+    assert!(!sim.truncated);
+}
+
+#[test]
+fn timeline_tracks_time_and_cursor() {
+    let root = repeat(
+        1,
+        vec![
+            move_to(100, 100, false, 0),
+            DelayAction {
+                random: false,
+                delay_ms_min: 250.into(),
+                delay_ms_max: 0.into(),
+            }
+            .into(),
+            press(Button::Left.into(), true, true, 40),
+            move_to(-10, 5, true, 0),
+        ],
+    );
+    let options = SimulationOptions {
+        start_location: (3, 4),
+        ..Default::default()
+    };
+
+    let sim = simulate(&root, options).unwrap();
+    let rows: Vec<_> = sim
+        .timeline
+        .iter()
+        .map(|e| (e.at, e.event, e.location))
+        .collect();
+    assert_eq!(
+        rows,
+        vec![
+            (
+                ms(0),
+                InputEvent::MoveMouse(100, 100, Coordinate::Abs),
+                (100, 100)
+            ),
+            (
+                ms(250),
+                InputEvent::Button(Button::Left, Direction::Press),
+                (100, 100)
+            ),
+            (
+                ms(290),
+                InputEvent::Button(Button::Left, Direction::Release),
+                (100, 100)
+            ),
+            (
+                ms(290),
+                InputEvent::MoveMouse(-10, 5, Coordinate::Rel),
+                (90, 105)
+            ),
+        ]
+    );
+    assert_eq!(sim.duration, ms(290));
+}
+
+#[test]
+fn infinite_macro_is_cut_off_by_duration() {
+    let mut root = repeat(
+        1,
+        vec![press(Button::Left.into(), true, true, 0), {
+            DelayAction {
+                random: false,
+                delay_ms_min: 1000.into(),
+                delay_ms_max: 0.into(),
+            }
+            .into()
+        }],
+    );
+    root.infinite = true;
+    let options = SimulationOptions {
+        max_duration: Duration::from_secs(10),
+        ..Default::default()
+    };
+
+    let sim = simulate(&root, options).unwrap();
+    assert!(sim.truncated);
+    assert_eq!(sim.duration, Duration::from_secs(10));
+    assert_eq!(sim.timeline.len(), 20);
+}
+
+#[test]
+fn infinite_macro_without_delays_is_cut_off_by_events() {
+    let mut root = repeat(1, vec![press(Key::Space.into(), true, true, 0)]);
+    root.infinite = true;
+    let options = SimulationOptions {
+        max_events: 100,
+        ..Default::default()
+    };
+
+    let sim = simulate(&root, options).unwrap();
+    assert!(sim.truncated);
+    assert_eq!(sim.timeline.len(), 100);
+}
+
+#[test]
+fn infinite_macro_without_events_or_time_is_cut_off_by_actions() {
+    let count: Action = IncrementAction {
+        name: "x".to_string(),
+        by: 1,
+    }
+    .into();
+    for action in [count, repeat(0, vec![]).into()] {
+        let mut root = repeat(1, vec![action]);
+        root.infinite = true;
+
+        let sim = simulate(&root, SimulationOptions::default()).unwrap();
+        assert!(sim.truncated);
+        assert!(sim.timeline.is_empty());
+    }
+}
+
+#[test]
+fn time_of_day_limits_count_from_the_start_time() {
+    let mut root = repeat(
+        1,
+        vec![DelayAction {
+            random: false,
+            delay_ms_min: 60_000.into(),
+            delay_ms_max: 0.into(),
+        }
+        .into()],
+    );
+    root.infinite = true;
+    root.time_limit = Some(TimeLimit::Until {
+        hour: 10,
+        minute: 30,
+        utc_offset_minutes: Some(0),
+    });
+    let options = SimulationOptions {
+        // 10:00 UTC
+        start_time: SystemTime::UNIX_EPOCH + Duration::from_secs(10 * 60 * 60),
+        ..Default::default()
+    };
+
+    let sim = simulate(&root, options).unwrap();
+    assert!(!sim.truncated);
+    assert_eq!(sim.duration, Duration::from_secs(30 * 60));
+}
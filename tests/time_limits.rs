@@ -0,0 +1,129 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::RecordingBackend;
+use auto_clicker::clock::{Clock, VirtualClock};
+use common::*;
+use std::time::{Duration, SystemTime};
+
+fn wait(ms: u64) -> Action {
+    DelayAction {
+        random: false,
+        delay_ms_min: ms.into(),
+        delay_ms_max: ms.into(),
+    }
+    .into()
+}
+
+/// Runs `root` and returns how long it took
+fn run_on(clock: VirtualClock, root: LoopAction) -> Duration {
+    let mut backend = RecordingBackend::new();
+    let mut ctx = ExecutionContext::new(&mut backend, &clock);
+    root.execute(&mut ctx).unwrap();
+    clock.now()
+}
+
+/// 10:00:00 UTC on the first day of the epoch
+fn ten_o_clock() -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(10 * 60 * 60)
+}
+
+#[test]
+fn infinite_loop_runs_for_the_time_limit() {
+    let mut root = repeat(0, vec![wait(1000)]);
+    root.infinite = true;
+    root.time_limit = Some(TimeLimit::For { secs: 10 });
+
+    assert_eq!(run_on(VirtualClock::new(), root), Duration::from_secs(10));
+}
+
+#[test]
+fn time_limit_is_checked_between_iterations() {
+    let mut root = repeat(0, vec![wait(3000)]);
+    root.infinite = true;
+    root.time_limit = Some(TimeLimit::For { secs: 10 });
+
+    // the iteration started at 9 s is not cut short
+    assert_eq!(run_on(VirtualClock::new(), root), Duration::from_secs(12));
+}
+
+#[test]
+fn iterations_end_the_loop_before_the_time_limit() {
+    let mut root = repeat(3, vec![wait(1000)]);
+    root.time_limit = Some(TimeLimit::For { secs: 60 });
+
+    assert_eq!(run_on(VirtualClock::new(), root), Duration::from_secs(3));
+}
+
+#[test]
+fn loop_runs_until_time_of_day() {
+    let mut root = repeat(0, vec![wait(60 * 1000)]);
+    root.infinite = true;
+    root.time_limit = Some(TimeLimit::Until {
+        hour: 12,
+        minute: 5,
+        utc_offset_minutes: Some(120),
+    });
+    let clock = VirtualClock::new().starting_at(ten_o_clock());
+
+    assert_eq!(run_on(clock, root), Duration::from_secs(5 * 60));
+}
+
+#[test]
+fn past_time_of_day_means_tomorrow() {
+    let clock = VirtualClock::new().starting_at(ten_o_clock() + Duration::from_millis(500));
+    let limit = TimeLimit::Until {
+        hour: 9,
+        minute: 59,
+        utc_offset_minutes: Some(0),
+    };
+
+    assert_eq!(
+        limit.remaining(&clock),
+        Duration::from_secs(23 * 60 * 60 + 59 * 60) - Duration::from_millis(500)
+    );
+}
+
+#[test]
+fn time_of_day_is_local_without_an_offset() {
+    let start = ten_o_clock() + Duration::from_millis(500);
+    let clock = VirtualClock::new().starting_at(start);
+    let local_offset = chrono::DateTime::<chrono::Local>::from(start)
+        .offset()
+        .local_minus_utc()
+        / 60;
+    let until = |utc_offset_minutes| TimeLimit::Until {
+        hour: 9,
+        minute: 59,
+        utc_offset_minutes,
+    };
+
+    assert_eq!(
+        until(None).remaining(&clock),
+        until(Some(local_offset as i16)).remaining(&clock)
+    );
+}
+
+#[test]
+fn offset_is_only_saved_when_fixed() {
+    let local = TimeLimit::Until {
+        hour: 17,
+        minute: 0,
+        utc_offset_minutes: None,
+    };
+    assert_eq!(
+        serde_json::to_string(&local).unwrap(),
+        r#"{"Until":{"hour":17,"minute":0}}"#
+    );
+
+    let fixed: TimeLimit =
+        serde_json::from_str(r#"{"Until":{"hour":17,"minute":0,"utc_offset_minutes":60}}"#)
+            .unwrap();
+    assert!(matches!(
+        fixed,
+        TimeLimit::Until {
+            utc_offset_minutes: Some(60),
+            ..
+        }
+    ));
+}
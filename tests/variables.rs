@@ -0,0 +1,223 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::{InputEvent, RecordingBackend};
+use auto_clicker::clock::VirtualClock;
+use auto_clicker::errors::AppError;
+use auto_clicker::expr::{Param, Value};
+use common::*;
+use enigo::{Coordinate, Key};
+use std::collections::HashMap;
+
+fn set(name: &str, value: &str) -> Action {
+    SetVariableAction {
+        name: name.to_string(),
+        value: value.to_string(),
+    }
+    .into()
+}
+
+fn increment(name: &str, by: i64) -> Action {
+    IncrementAction {
+        name: name.to_string(),
+        by,
+    }
+    .into()
+}
+
+fn move_to_expr(x: &str, y: &str) -> Action {
+    MoveAction {
+        x: Param::Expr(x.to_string()),
+        y: Param::Expr(y.to_string()),
+        relative: false,
+        move_time_ms: 0.into(),
+        delay_after_ms: 0.into(),
+        path: MovePath::default(),
+    }
+    .into()
+}
+
+/// Runs `root` and returns the variables it left behind
+fn run_with_variables(
+    root: LoopAction,
+) -> Result<(RecordingBackend, HashMap<String, Value>), AppError> {
+    let clock = VirtualClock::new();
+    let mut backend = RecordingBackend::new().with_clock(clock.clone());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock);
+    root.execute(&mut ctx)?;
+    let variables = ctx.variables.clone();
+    Ok((backend, variables))
+}
+
+#[test]
+fn arithmetic_follows_precedence() {
+    assert_eq!(eval("100 + x * 40").unwrap(), Value::Int(380));
+    assert_eq!(eval("(100 + x) * 2").unwrap(), Value::Int(214));
+    assert_eq!(eval("-x - -3").unwrap(), Value::Int(-4));
+    assert_eq!(eval("20 / 3 + 20 % 3").unwrap(), Value::Int(8));
+    assert_eq!(eval("10 - 4 - 3").unwrap(), Value::Int(3));
+}
+
+#[test]
+fn strings_are_joined() {
+    assert_eq!(
+        eval(r#""hi " + name + x"#).unwrap(),
+        Value::Str("hi bob7".into())
+    );
+}
+
+#[test]
+fn invalid_expressions_are_errors() {
+    for source in [
+        "", "1 +", "(1", "1 2", "y + 1", "1 / 0", "name * 2", "\"open",
+    ] {
+        assert!(
+            matches!(eval(source), Err(AppError::Expression(_))),
+            "{:?}",
+            source
+        );
+    }
+}
+
+#[test]
+fn coordinates_follow_the_iteration() {
+    let root = repeat(3, vec![move_to_expr("100 + i * 40", "iteration")]);
+
+    let backend = run(root, RecordingBackend::new());
+
+    assert_eq!(
+        backend.inputs(),
+        vec![
+            InputEvent::MoveMouse(100, 0, Coordinate::Abs),
+            InputEvent::MoveMouse(140, 1, Coordinate::Abs),
+            InputEvent::MoveMouse(180, 2, Coordinate::Abs),
+        ]
+    );
+}
+
+#[test]
+fn counters_and_alternating_positions() {
+    let root = repeat(
+        4,
+        vec![
+            increment("clicks", 1),
+            set("x", "clicks % 2 * 50"),
+            move_to_expr("x", "0"),
+        ],
+    );
+
+    let (backend, variables) = run_with_variables(root).unwrap();
+
+    let xs: Vec<i32> = backend
+        .inputs()
+        .into_iter()
+        .map(|event| match event {
+            InputEvent::MoveMouse(x, _, _) => x,
+            other => panic!("unexpected {:?}", other),
+        })
+        .collect();
+    assert_eq!(xs, vec![50, 0, 50, 0]);
+    assert_eq!(variables["clicks"], Value::Int(4));
+    assert_eq!(variables["x"], Value::Int(0));
+}
+
+#[test]
+fn loop_iterations_from_a_variable() {
+    let mut inner = repeat(0, vec![press(Key::Space.into(), true, true, 0)]);
+    inner.iterations = Param::Expr("n + 1".to_string());
+    let root = repeat(1, vec![set("n", "2"), inner.into()]);
+
+    let (backend, _) = run_with_variables(root).unwrap();
+
+    assert_eq!(backend.inputs().len(), 6);
+}
+
+#[test]
+fn times_from_expressions() {
+    let tab = PressAction {
+        keybutton: Key::Tab.into(),
+        down: true,
+        up: true,
+        hold_time_ms: Param::Expr("10 * (i + 1)".to_string()),
+        delay_after_ms: Param::Expr("wait".to_string()),
+    };
+    let delay = DelayAction {
+        random: false,
+        delay_ms_min: Param::Expr("wait * 2".to_string()),
+        delay_ms_max: 0.into(),
+    };
+    let root = repeat(2, vec![set("wait", "5"), tab.into(), delay.into()]);
+
+    let (backend, _) = run_with_variables(root).unwrap();
+
+    let times: Vec<_> = backend.events().iter().map(|e| e.at).collect();
+    assert_eq!(times, vec![ms(0), ms(10), ms(25), ms(45)]);
+}
+
+#[test]
+fn iteration_in_a_branch_is_the_one_of_the_loop() {
+    let mut ifaction = IfAction::new(Condition::Elapsed { ms: 0 });
+    ifaction.then_branch.actions = vec![move_to_expr("i", "0")];
+    let root = repeat(2, vec![ifaction.into()]);
+
+    let backend = run(root, RecordingBackend::new());
+
+    assert_eq!(
+        backend.inputs(),
+        vec![
+            InputEvent::MoveMouse(0, 0, Coordinate::Abs),
+            InputEvent::MoveMouse(1, 0, Coordinate::Abs),
+        ]
+    );
+}
+
+#[test]
+fn unknown_variable_fails_the_action() {
+    let root = repeat(1, vec![increment("n", 1), move_to_expr("m", "0")]);
+
+    let err = run_with_variables(root).map(|_| ()).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "Action 1.1 failed: Expression error: unknown variable `m`"
+    );
+}
+
+#[test]
+fn out_of_range_values_fail_the_action() {
+    let delay = DelayAction {
+        random: false,
+        delay_ms_min: Param::Expr("0 - 5000".to_string()),
+        delay_ms_max: 0.into(),
+    };
+    let root = repeat(1, vec![delay.into()]);
+
+    let err = run_with_variables(root).map(|_| ()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Action 1.0 failed: Expression error: `0 - 5000` is -5000, which is out of range"
+    );
+
+    let root = repeat(1, vec![move_to_expr("9999999999", "0")]);
+    let err = run_with_variables(root).map(|_| ()).unwrap_err();
+    assert!(err.to_string().starts_with("Action 1.0 failed"));
+}
+
+#[test]
+fn the_iteration_cannot_be_set() {
+    let root = repeat(1, vec![set("i", "3")]);
+
+    assert!(run_with_variables(root).is_err());
+}
+
+#[test]
+fn expressions_are_saved_as_text() {
+    let action = move_to_expr("100 + i * 40", "5");
+    let json = serde_json::to_string(&action).unwrap();
+    assert!(json.contains(r#""x":"100 + i * 40""#));
+
+    let Action::Move(loaded) = serde_json::from_str(&json).unwrap() else {
+        panic!("not a move");
+    };
+    assert_eq!(loaded.x, Param::Expr("100 + i * 40".to_string()));
+}
@@ -0,0 +1,202 @@
+mod common;
+
+use auto_clicker::actions::*;
+use auto_clicker::backend::{InputEvent, RecordingBackend};
+use auto_clicker::clock::{Clock, VirtualClock};
+use auto_clicker::errors::AppError;
+use auto_clicker::screen::{Color, Screen};
+use common::*;
+use enigo::{Direction, Key};
+use std::time::Duration;
+
+fn wait_until(condition: Condition, timeout_ms: u64, on_timeout: OnTimeout) -> WaitUntilAction {
+    WaitUntilAction {
+        condition,
+        poll_interval_ms: 100,
+        timeout_ms,
+        on_timeout,
+        timeout_branch: LoopAction::once(vec![press(Key::Escape.into(), true, true, 0)]),
+    }
+}
+
+/// Turns red once the clock reaches `red_at`
+struct ClockScreen {
+    clock: VirtualClock,
+    red_at: Duration,
+}
+
+impl Screen for ClockScreen {
+    fn pixel(&self, _x: i32, _y: i32) -> Result<Color, AppError> {
+        if self.clock.now() >= self.red_at {
+            Ok(Color::rgb(255, 0, 0))
+        } else {
+            Ok(Color::rgb(0, 0, 0))
+        }
+    }
+}
+
+fn red_pixel() -> Condition {
+    Condition::PixelColor {
+        x: 0,
+        y: 0,
+        radius: 0,
+        color: Color::rgb(255, 0, 0),
+        tolerance: 0,
+    }
+}
+
+/// Time of every key press
+fn key_presses(backend: &RecordingBackend) -> Vec<(Duration, Key)> {
+    backend
+        .events()
+        .iter()
+        .filter_map(|recorded| match recorded.event {
+            InputEvent::Key(key, Direction::Press) => Some((recorded.at, key)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn continues_at_the_first_poll_that_sees_the_condition() {
+    let clock = VirtualClock::new();
+    let screen = ClockScreen {
+        clock: clock.clone(),
+        red_at: ms(250),
+    };
+    let mut backend = RecordingBackend::new().with_clock(clock.clone());
+    let root = repeat(
+        1,
+        vec![
+            wait_until(red_pixel(), 1000, OnTimeout::Stop).into(),
+            press(Key::Space.into(), true, true, 0),
+        ],
+    );
+    let mut ctx = ExecutionContext::new(&mut backend, &clock).with_screen(&screen);
+
+    root.execute(&mut ctx).unwrap();
+
+    assert_eq!(key_presses(&backend), vec![(ms(300), Key::Space)]);
+}
+
+#[test]
+fn waits_for_the_elapsed_time() {
+    let root = repeat(
+        1,
+        vec![
+            wait_until(Condition::Elapsed { ms: 150 }, 0, OnTimeout::Continue).into(),
+            press(Key::Space.into(), true, true, 0),
+        ],
+    );
+
+    let backend = run(root, RecordingBackend::new());
+
+    assert_eq!(key_presses(&backend), vec![(ms(200), Key::Space)]);
+}
+
+#[test]
+fn cursor_in_region() {
+    let in_region = Condition::CursorIn {
+        x: 10,
+        y: 10,
+        width: 5,
+        height: 5,
+    };
+    let root = repeat(
+        1,
+        vec![
+            // the move happens in the timeout branch, the second wait sees it at once
+            WaitUntilAction {
+                timeout_branch: LoopAction::once(vec![move_to(12, 14, false, 0)]),
+                ..wait_until(in_region.clone(), 300, OnTimeout::RunBranch)
+            }
+            .into(),
+            wait_until(in_region, 300, OnTimeout::Stop).into(),
+            press(Key::Space.into(), true, true, 0),
+        ],
+    );
+
+    let backend = run(root, RecordingBackend::new().with_location(15, 12));
+
+    assert_eq!(key_presses(&backend), vec![(ms(300), Key::Space)]);
+}
+
+#[test]
+fn timeout_continues_with_the_next_action() {
+    let root = repeat(
+        1,
+        vec![
+            wait_until(Condition::Elapsed { ms: 10_000 }, 250, OnTimeout::Continue).into(),
+            press(Key::Space.into(), true, true, 0),
+        ],
+    );
+
+    let backend = run(root, RecordingBackend::new());
+
+    // the last poll is shortened to the timeout
+    assert_eq!(key_presses(&backend), vec![(ms(250), Key::Space)]);
+}
+
+#[test]
+fn timeout_runs_the_branch() {
+    let root = repeat(
+        1,
+        vec![
+            wait_until(Condition::Elapsed { ms: 10_000 }, 250, OnTimeout::RunBranch).into(),
+            press(Key::Space.into(), true, true, 0),
+        ],
+    );
+
+    let backend = run(root, RecordingBackend::new());
+
+    assert_eq!(
+        key_presses(&backend),
+        vec![(ms(250), Key::Escape), (ms(250), Key::Space)]
+    );
+}
+
+#[test]
+fn timeout_stops_the_macro_with_an_error() {
+    let clock = VirtualClock::new();
+    let mut backend = RecordingBackend::new().with_clock(clock.clone());
+    let root = repeat(
+        1,
+        vec![
+            wait_until(Condition::Elapsed { ms: 10_000 }, 250, OnTimeout::Stop).into(),
+            press(Key::Space.into(), true, true, 0),
+        ],
+    );
+    let mut ctx = ExecutionContext::new(&mut backend, &clock);
+
+    let err = root.execute(&mut ctx).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "Action 1.0 failed: Gave up waiting after 250 ms"
+    );
+    assert!(backend.events().is_empty());
+}
+
+#[test]
+fn timeout_branch_is_numbered_like_a_nested_loop() {
+    let root = repeat(
+        1,
+        vec![
+            wait_until(Condition::Elapsed { ms: 10_000 }, 100, OnTimeout::RunBranch).into(),
+            repeat(1, vec![press(Key::Space.into(), true, true, 0)]).into(),
+        ],
+    );
+    let clock = VirtualClock::new();
+    let mut backend = RecordingBackend::new().with_clock(clock.clone());
+    let mut ctx = ExecutionContext::new(&mut backend, &clock).with_timeline();
+
+    root.execute(&mut ctx).unwrap();
+
+    let paths: Vec<String> = ctx
+        .timeline
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.path)
+        .collect();
+    assert_eq!(paths, vec!["2.0", "2.0", "3.0", "3.0"]);
+}